@@ -0,0 +1,196 @@
+//! A module for loading persistent default flags from a config file.
+//!
+//! Config files use a flat subset of TOML, where every key is the long name of
+//! a command line flag:
+//!
+//! ```toml
+//! # ~/.dotcodeschool.toml
+//! locale = "fr"
+//! ascii = true
+//! ```
+//!
+//! Keys can use either underscores or dashes. Boolean flags are enabled with
+//! `true` and left to their default with `false`, arrays stand for a flag
+//! repeated once for each value. Settings only act as defaults: flags passed
+//! on the command line or through environment variables always take
+//! precedence over the config file, and boolean flags it enables can be
+//! turned off with `--flag=false`.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Name of the config file looked for in the current and home directories.
+pub const CONFIG_FILE: &str = ".dotcodeschool.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to read config file at {path}: {source}")]
+    ReadError { path: String, source: std::io::Error },
+    #[error("invalid config file {path} at line {line}: {msg}")]
+    SyntaxError { path: String, line: usize, msg: String },
+}
+
+/// Looks for a config file, first in the current directory and then in the
+/// home directory.
+pub fn find_config() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    [Some(PathBuf::from(".")), home]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Loads a config file, returning the long name of each flag it sets along
+/// with its values.
+///
+/// Boolean flags have the value `true` or `false`, other flags one value per
+/// item of their array or a single value otherwise.
+///
+/// * `path`: path to the config file.
+pub fn load_config(
+    path: &Path,
+) -> Result<Vec<(String, Vec<String>)>, ConfigError> {
+    let display = path.display().to_string();
+
+    let contents = std::fs::read_to_string(path).map_err(|err| {
+        ConfigError::ReadError { path: display.clone(), source: err }
+    })?;
+
+    let mut settings = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let syntax_error = |msg: &str| ConfigError::SyntaxError {
+            path: display.clone(),
+            line: index + 1,
+            msg: msg.to_string(),
+        };
+
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(syntax_error("expected 'key = value'"))?;
+        let flag = key.trim().replace('_', "-");
+        let value = value.trim();
+
+        let values = match value.strip_prefix('[') {
+            Some(array) => split_array(
+                array
+                    .strip_suffix(']')
+                    .ok_or(syntax_error("unterminated array"))?,
+            ),
+            None => vec![value],
+        };
+
+        let values = values
+            .into_iter()
+            .map(|value| match value {
+                "true" | "false" => Ok(value.to_string()),
+                value => parse_value(value).ok_or(syntax_error(&format!(
+                    "invalid value '{value}' for '{}'",
+                    key.trim()
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        settings.push((flag, values));
+    }
+
+    Ok(settings)
+}
+
+/// Renders settings in the format of config files, so that they can be copied
+/// into one.
+///
+/// Settings which are not set are left out.
+///
+/// * `settings`: settings to render, as a JSON object keyed by flag name.
+pub fn to_config(settings: &Value) -> String {
+    let settings = settings.as_object().into_iter().flatten();
+
+    settings
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect()
+}
+
+/// Removes a trailing `#` comment from a line, ignoring `#` inside of quotes.
+///
+/// * `line`: line to strip.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '#') => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Splits the values of an array on commas, ignoring commas inside of quotes.
+///
+/// * `array`: contents of the array, without its brackets.
+fn split_array(array: &str) -> Vec<&str> {
+    let mut quote = None;
+    let mut values = Vec::new();
+    let mut start = 0;
+
+    for (index, c) in array.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, ',') => {
+                values.push(&array[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(&array[start..]);
+
+    values
+        .into_iter()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Parses a string or number value.
+///
+/// * `value`: raw value, strings being surrounded by single or double quotes.
+fn parse_value(value: &str) -> Option<String> {
+    let unquote = |quote: char| {
+        value.strip_prefix(quote).and_then(|value| value.strip_suffix(quote))
+    };
+
+    match (unquote('"'), unquote('\'')) {
+        (Some(value), _) => {
+            Some(value.replace("\\\"", "\"").replace("\\\\", "\\"))
+        }
+        (None, Some(value)) => Some(value.to_string()),
+        _ => value.parse::<f64>().ok().map(|_| value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrays_split_outside_of_quotes() {
+        assert_eq!(
+            split_array(r#""cargo test --features a,b", 'x,y' , 3,"#),
+            [r#""cargo test --features a,b""#, "'x,y'", "3"]
+        );
+    }
+}
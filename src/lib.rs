@@ -0,0 +1,48 @@
+//! Library API of the DotCodeSchool CLI, for running courses from other
+//! programs.
+//!
+//! A [runner::RunnerVersion] can either be driven one state at a time with
+//! [runner::Runner::run], or consumed as an iterator yielding the
+//! [runner::TestOutcome] of each test as it completes:
+//!
+//! ```no_run
+//! use dotcodeschool_cli::{
+//!     parsing::CourseSource,
+//!     runner::{Runner, RunnerOptions, RunnerVersion},
+//! };
+//!
+//! let source = CourseSource::File("./tests.json".to_string());
+//! let options = RunnerOptions { score_only: true, ..Default::default() };
+//!
+//! for outcome in RunnerVersion::new(&source, options) {
+//!     println!("{} / {}: {:?}", outcome.suite, outcome.test, outcome.status);
+//! }
+//! ```
+//!
+//! Output is displayed in the terminal, or written to any [std::io::Write]
+//! with [runner::RunnerVersion::from_course_with_writer] so that it can be
+//! kept:
+//!
+//! ```no_run
+//! use dotcodeschool_cli::{
+//!     parsing::CourseSource,
+//!     runner::{RunnerOptions, RunnerVersion},
+//! };
+//!
+//! let course = CourseSource::File("./tests.json".to_string()).load()?;
+//! let log = std::fs::File::create("run.log")?;
+//! let runner = RunnerVersion::from_course_with_writer(
+//!     course,
+//!     RunnerOptions::default(),
+//!     Box::new(log),
+//! );
+//! let outcomes = runner.run_all();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Single tests can also be run on their own with [runner::run_test], without
+//! loading a whole course.
+
+pub mod locale;
+pub mod parsing;
+pub mod runner;
@@ -0,0 +1,277 @@
+//! A module for translating user-facing messages.
+//!
+//! Only messages displayed by the CLI itself are translated, messages provided
+//! by a course such as `message_on_fail` are displayed as authored. Messages
+//! containing placeholders such as `{count}` are formatted with
+//! [Catalog::format].
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Languages available for user-facing messages.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+/// All user-facing messages in a given [Locale].
+pub struct Catalog {
+    pub by: &'static str,
+    pub exercises_left: &'static str,
+    pub git_missing: &'static str,
+    pub not_a_repo: &'static str,
+    pub wrong_branch: &'static str,
+    pub rustc_missing: &'static str,
+    pub rustc_too_old: &'static str,
+    pub missing_binaries: &'static str,
+    pub downloading_tests: &'static str,
+    pub checking_updates: &'static str,
+    pub updating_tests: &'static str,
+    pub updated_tests: &'static str,
+    pub update_failed: &'static str,
+    pub running_test: &'static str,
+    pub running_tests: &'static str,
+    pub passing_so_far: &'static str,
+    pub new_badge: &'static str,
+    pub optional: &'static str,
+    pub failed_test: &'static str,
+    pub skipped: &'static str,
+    pub mandatory_passed: &'static str,
+    pub optional_skipped: &'static str,
+    pub skip_not_allowed: &'static str,
+    pub prerequisite_failed: &'static str,
+    pub before_start: &'static str,
+    pub starting_at: &'static str,
+    pub previous_run: &'static str,
+    pub no_previous_run: &'static str,
+    pub passed: &'static str,
+    pub failed: &'static str,
+    pub error: &'static str,
+    pub load_failed: &'static str,
+    pub no_course: &'static str,
+    pub reload_failed: &'static str,
+    pub final_score: &'static str,
+    pub no_tests_run: &'static str,
+    pub total_time: &'static str,
+    pub slowest_tests: &'static str,
+    pub scoring: &'static str,
+    pub not_counted: &'static str,
+    pub bonus_point: &'static str,
+    pub bonus_passed: &'static str,
+    pub not_run: &'static str,
+    pub scoring_formula: &'static str,
+    pub results_by_suite: &'static str,
+    pub results_by_tag: &'static str,
+    pub score_messages: &'static [(u32, &'static str)],
+    pub bonus_missed: &'static str,
+    pub course_valid: &'static str,
+    pub course_problems: &'static str,
+    pub course_exists: &'static str,
+    pub write_failed: &'static str,
+    pub course_created: &'static str,
+    pub course_guide: &'static str,
+}
+
+const EN: Catalog = Catalog {
+    by: "by",
+    exercises_left: "You have {count} exercises left",
+    git_missing: "Could not find git, make sure it is installed",
+    not_a_repo: "Not inside of a git repository, are you in the right \
+                 directory?",
+    wrong_branch: "Expected to be on branch '{expected}' but currently on \
+                   '{found}'",
+    rustc_missing: "Could not find rustc, make sure Rust is installed",
+    rustc_too_old: "This course needs Rust {expected} or newer but {found} is \
+                    installed, update it with `rustup update`",
+    missing_binaries: "Could not find the following commands, make sure they \
+                       are installed: {binaries}",
+    downloading_tests: "Downloading tests",
+    checking_updates: "Checking for updates",
+    updating_tests: "Updating tests",
+    updated_tests: "Updated tests",
+    update_failed: "Failed to check for updates",
+    running_test: "Running test",
+    running_tests: "Running {count} tests",
+    passing_so_far: "{passed}/{count} passing so far",
+    new_badge: "NEW",
+    optional: "(optional)",
+    failed_test: "Failed test {test}",
+    skipped: "Skipped",
+    mandatory_passed: "all mandatory tests have passed",
+    optional_skipped: "optional tests are skipped",
+    skip_not_allowed: "mandatory tests cannot be skipped, this one was \
+                       skipped because {reason}",
+    prerequisite_failed: "prerequisite suite '{suite}' failed",
+    before_start: "before the suite the run started at",
+    starting_at: "Starting at suite {suite}, earlier suites are skipped",
+    previous_run: "Kept from the previous run",
+    no_previous_run: "No previous run found, run the tests at least once first",
+    passed: "Passed",
+    failed: "Failed",
+    error: "Error",
+    load_failed: "Could not load the course, no tests were run",
+    no_course: "No course found, there is no tests.json in this directory or \
+                its parents.\n\nTo get started:\n    \
+                - run the CLI from the directory of a course\n    \
+                - or give it a course file with --tests <FILE>\n    \
+                - or write a new course with `dotcodeschool-cli init`",
+    reload_failed: "Could not reload the course, running it as it was before",
+    final_score: "final score",
+    no_tests_run: "no tests were run",
+    total_time: "total time",
+    slowest_tests: "Slowest tests",
+    scoring: "How the score was computed",
+    not_counted: "not counted",
+    bonus_point: "bonus",
+    bonus_passed: "{count} optional test(s) passed as a bonus",
+    not_run: "{count} test(s) not run, as the run stopped early",
+    scoring_formula: "{passed} passed / {counted} counted ({total} tests - \
+                      {skipped} skipped) × 100 = {score}%",
+    results_by_suite: "Results by suite",
+    results_by_tag: "Results by tag",
+    score_messages: &[
+        (90, "Great job!"),
+        (60, "Almost there!"),
+        (0, "Keep going!"),
+    ],
+    bonus_missed: "Bonus opportunities missed",
+    course_valid: "Course is valid",
+    course_problems: "Found {count} problem(s) in the course:",
+    course_exists: "{path} already exists, use --force to overwrite it",
+    write_failed: "Failed to write {path}: {err}",
+    course_created: "Created {path}",
+    course_guide: "- version: format version of the course file, leave it as \
+                   is.\n\
+                   - course, instructor, course_id: information about your \
+                   course.\n\
+                   - suites: groups of tests, run in order. Optional suites \
+                   do not need to pass\n  for the course to be validated.\n\
+                   - tests: each test runs `cmd` and passes if it exits \
+                   successfully. Tests can\n  also be marked as optional, and \
+                   display `message_on_success` or\n  `message_on_fail` \
+                   depending on the result.",
+};
+
+const FR: Catalog = Catalog {
+    by: "par",
+    exercises_left: "Il vous reste {count} exercices",
+    git_missing: "Impossible de trouver git, vérifiez qu'il est installé",
+    not_a_repo: "Pas dans un dépôt git, êtes-vous dans le bon dossier ?",
+    wrong_branch: "La branche '{expected}' était attendue mais la branche \
+                   actuelle est '{found}'",
+    rustc_missing: "Impossible de trouver rustc, vérifiez que Rust est \
+                    installé",
+    rustc_too_old: "Ce cours nécessite Rust {expected} ou plus récent mais \
+                    {found} est installé, mettez-le à jour avec \
+                    `rustup update`",
+    missing_binaries: "Impossible de trouver les commandes suivantes, \
+                       vérifiez qu'elles sont installées : {binaries}",
+    downloading_tests: "Téléchargement des tests",
+    checking_updates: "Recherche de mises à jour",
+    updating_tests: "Mise à jour des tests",
+    updated_tests: "Tests mis à jour",
+    update_failed: "Impossible de rechercher des mises à jour",
+    running_test: "Exécution du test",
+    running_tests: "Exécution de {count} tests",
+    passing_so_far: "{passed}/{count} réussis pour l'instant",
+    new_badge: "NOUVEAU",
+    optional: "(optionnel)",
+    failed_test: "Échec du test {test}",
+    skipped: "Ignoré",
+    mandatory_passed: "tous les tests obligatoires sont validés",
+    optional_skipped: "les tests optionnels sont ignorés",
+    skip_not_allowed: "les tests obligatoires ne peuvent pas être ignorés, \
+                       celui-ci l'a été car {reason}",
+    prerequisite_failed: "la suite prérequise '{suite}' a échoué",
+    before_start: "avant la suite de départ de l'exécution",
+    starting_at: "Départ à la suite {suite}, les suites précédentes sont \
+                  ignorées",
+    previous_run: "Conservé de l'exécution précédente",
+    no_previous_run: "Aucune exécution précédente trouvée, lancez d'abord \
+                      les tests au moins une fois",
+    passed: "Réussi",
+    failed: "Échoué",
+    error: "Erreur",
+    load_failed: "Impossible de charger le cours, aucun test n'a été \
+                  exécuté",
+    no_course: "Aucun cours trouvé, il n'y a pas de tests.json dans ce \
+                dossier ni ses parents.\n\nPour commencer :\n    \
+                - lancez la CLI depuis le dossier d'un cours\n    \
+                - ou indiquez-lui un fichier de cours avec \
+                --tests <FICHIER>\n    \
+                - ou écrivez un nouveau cours avec `dotcodeschool-cli init`",
+    reload_failed: "Impossible de recharger le cours, il est exécuté tel \
+                    qu'il était avant",
+    final_score: "score final",
+    no_tests_run: "aucun test n'a été exécuté",
+    total_time: "temps total",
+    slowest_tests: "Tests les plus lents",
+    scoring: "Calcul du score",
+    not_counted: "non compté",
+    bonus_point: "bonus",
+    bonus_passed: "{count} test(s) optionnel(s) réussi(s) en bonus",
+    not_run: "{count} test(s) non exécuté(s), l'exécution s'étant arrêtée \
+              plus tôt",
+    scoring_formula: "{passed} réussi(s) / {counted} compté(s) ({total} tests \
+                      - {skipped} ignoré(s)) × 100 = {score}%",
+    results_by_suite: "Résultats par suite",
+    results_by_tag: "Résultats par tag",
+    score_messages: &[
+        (90, "Excellent travail !"),
+        (60, "Vous y êtes presque !"),
+        (0, "Continuez comme ça !"),
+    ],
+    bonus_missed: "Points bonus manqués",
+    course_valid: "Le cours est valide",
+    course_problems: "{count} problème(s) trouvé(s) dans le cours :",
+    course_exists: "{path} existe déjà, utilisez --force pour l'écraser",
+    write_failed: "Impossible d'écrire {path} : {err}",
+    course_created: "{path} créé",
+    course_guide: "- version : version du format du fichier de cours, à \
+                   laisser telle quelle.\n\
+                   - course, instructor, course_id : informations sur votre \
+                   cours.\n\
+                   - suites : groupes de tests, exécutés dans l'ordre. Les \
+                   suites optionnelles\n  n'ont pas besoin de réussir pour \
+                   que le cours soit validé.\n\
+                   - tests : chaque test exécute `cmd` et réussit si la \
+                   commande se termine\n  avec succès. Les tests peuvent \
+                   aussi être optionnels, et affichent\n  \
+                   `message_on_success` ou `message_on_fail` selon le \
+                   résultat.",
+};
+
+impl Locale {
+    /// Determines the [Locale] from the `LANG` environment variable, falling
+    /// back to [Locale::En] if it is not set or not supported.
+    pub fn from_env() -> Self {
+        let lang = std::env::var("LANG").unwrap_or_default();
+
+        match lang.split(['_', '.']).next() {
+            Some("fr") => Locale::Fr,
+            _ => Locale::En,
+        }
+    }
+
+    /// Returns the messages for this [Locale].
+    pub fn catalog(&self) -> &'static Catalog {
+        match self {
+            Locale::En => &EN,
+            Locale::Fr => &FR,
+        }
+    }
+}
+
+impl Catalog {
+    /// Replaces a `{key}` placeholder in a message with its value.
+    ///
+    /// * `msg`: message containing the placeholder.
+    /// * `key`: name of the placeholder, without braces.
+    /// * `value`: value to substitute.
+    pub fn format(msg: &str, key: &str, value: &str) -> String {
+        msg.replace(&format!("{{{key}}}"), value)
+    }
+}
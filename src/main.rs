@@ -1,23 +1,432 @@
 use chrono::Local;
-use clap::Parser;
+use clap::{
+    builder::BoolishValueParser, error::ErrorKind, parser::ValueSource, Arg,
+    ArgAction, CommandFactory, FromArgMatches, Id, Parser, Subcommand,
+};
+use config::{find_config, load_config, to_config};
+use dotcodeschool_cli::{
+    locale::{Catalog, Locale},
+    parsing::{
+        v1::{parse_version, JsonCourseV1},
+        CourseSource, JsonCourseVersion, LoadOptions,
+    },
+    runner::{
+        recording, report_load_error,
+        state::{RunState, STATE_FILE},
+        ColorPassthrough, ExitReason, Glyphs, GroupBy, Runner, RunnerOptions,
+        RunnerVersion, TestRunnerState, ThemeName,
+    },
+};
 use env_logger::Builder;
-use runner::{Runner, RunnerVersion, TestRunnerState};
-use std::io::Write;
+use log::LevelFilter;
+use serde::Serialize;
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
 
-mod parsing;
-mod runner;
+mod config;
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+const DEFAULT_TESTS: &str = "./tests.json";
+/// How long to wait before running a test again with `--until-pass`.
+const UNTIL_PASS_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Serialize, Debug)]
+#[command(version, about, long_about = None, args_override_self = true)]
+#[command(after_help = "Exit codes:
+  0  every mandatory test passed
+  1  a mandatory test failed
+  2  the course could not be loaded, or invalid arguments or config file
+  3  a mandatory test timed out
+  4  a check failed before any test ran
+  5  no course file was given and none was found")]
 struct Args {
-    #[arg(short, long)]
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+
+    /// Config file with default flags, defaults to `.dotcodeschool.toml` in
+    /// the current directory or the home directory.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Path to the course file, defaults to `./tests.json` in the current
+    /// directory or the closest of its parents within the git repository. A
+    /// directory is loaded as a course split into a `course.json` file and
+    /// one `suite-*.json` file per suite.
+    #[arg(short, long, env = "DOTCODESCHOOL_TESTS", global = true)]
     tests: Option<String>,
+
+    /// Directory of course files to run as a single course, in order of file
+    /// name. Takes precedence over `--tests`.
+    #[arg(long, global = true)]
+    tests_dir: Option<String>,
+
+    /// Language of user-facing messages, defaults to the `LANG` environment
+    /// variable.
+    #[arg(long, value_enum)]
+    locale: Option<Locale>,
+
+    /// Only prints the final score, for use in scripts.
+    #[arg(long)]
+    print_score_only: bool,
+
+    /// Replaces all output with a JSON summary of the run.
+    #[arg(long, conflicts_with = "print_score_only")]
+    json: bool,
+
+    /// Replaces emojis and box-drawing characters with plain ASCII.
+    #[arg(long)]
+    ascii: bool,
+
+    /// Colors and styles used for output.
+    #[arg(long, value_enum, default_value_t)]
+    theme: ThemeName,
+
+    /// Displays test output live as it is produced.
+    #[arg(long)]
+    stream: bool,
+
+    /// Hides the progress bar while still displaying test output, for logs
+    /// which do not handle terminal control codes.
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Only displays the progress bar while tests run, then displays all
+    /// results grouped by status.
+    #[arg(long, conflicts_with = "stream")]
+    buffered: bool,
+
+    /// Displays each test on a single line, followed by its output only if it
+    /// failed.
+    #[arg(long, conflicts_with = "stream")]
+    compact: bool,
+
+    /// Whether colors printed by tests are kept in their output. By default,
+    /// they are kept in a terminal and stripped otherwise.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t)]
+    color_output_passthrough: ColorPassthrough,
+
+    /// Maximum number of tests of a suite run at the same time.
+    #[arg(short, long, value_name = "COUNT", default_value_t = 1)]
+    #[arg(conflicts_with = "stream")]
+    jobs: usize,
+
+    /// Maximum number of suites run at the same time, their tests still being
+    /// displayed in order.
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    #[arg(conflicts_with = "stream")]
+    jobs_suite: usize,
+
+    /// Directory of fixture files copied into the current directory before
+    /// each test runs, and removed once it has finished.
+    #[arg(long, value_name = "DIR")]
+    fixtures: Option<PathBuf>,
+
+    /// Directory which the working directory of tests is relative to, such as
+    /// the root of a monorepo. Defaults to the current directory.
+    #[arg(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    /// Only allows tests to run this binary, can be repeated. Meant for
+    /// courses which are not trusted, any other binary failing the test.
+    #[arg(long = "allow-cmd", value_name = "BINARY")]
+    #[serde(rename = "allow_cmd")]
+    allow_cmds: Vec<String>,
+
+    /// Fails the run instead of warning when the Rust toolchain is older than
+    /// the course requires.
+    #[arg(long)]
+    strict_toolchain: bool,
+
+    /// Checks that every command run by the tests can be found before running
+    /// any of them, and fails the run listing those which cannot.
+    #[arg(long)]
+    preflight: bool,
+
+    /// Displays how many tests have run in the title of the terminal.
+    #[arg(long)]
+    title_progress: bool,
+
+    /// Fails tests which take longer than their expected duration instead of
+    /// only displaying a warning.
+    #[arg(long)]
+    strict_perf: bool,
+
+    /// Lists the slowest tests at the end of the run, 10 unless a count is
+    /// given.
+    #[arg(long, value_name = "COUNT", num_args = 0..=1)]
+    #[arg(default_missing_value = "10")]
+    profile: Option<usize>,
+
+    /// Displays how each test contributed to the score at the end of the run,
+    /// and how the final percentage was computed.
+    #[arg(long)]
+    explain_scoring: bool,
+
+    /// Records the output of the run to this file, to be replayed with
+    /// `--replay`.
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Displays a run recorded with `--record` again with the same timing,
+    /// without running any test.
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// Displays how many tests of each suite or tag passed at the end of the
+    /// run.
+    #[arg(long, value_enum, value_name = "GROUP")]
+    group_by: Option<GroupBy>,
+
+    /// Writes a Markdown report of the run to this file.
+    #[arg(long, value_name = "PATH")]
+    markdown: Option<PathBuf>,
+
+    /// Writes a badge of the score to this file, for embedding in a README:
+    /// an SVG image if it ends in `.svg`, a shields.io endpoint otherwise.
+    #[arg(long, value_name = "PATH")]
+    badge: Option<PathBuf>,
+
+    /// Writes the JSON summary of the run to this file, keeping the usual
+    /// terminal output.
+    #[arg(long, value_name = "PATH")]
+    summary_json_path: Option<PathBuf>,
+
+    /// Captures the error output of tests along with their standard output,
+    /// like `2>&1`, so that lines are displayed in the order they were printed.
+    #[arg(long)]
+    merge_output: bool,
+
+    /// Number of unchanged lines displayed around each change when the output
+    /// of a test differs from what was expected, like `diff -U`.
+    #[arg(long, value_name = "LINES", default_value_t = 3)]
+    diff_context: usize,
+
+    /// Runs tests attached to a pseudo-terminal, so that tools such as cargo
+    /// still display colors. Error output is captured along with the standard
+    /// output. Only supported on Unix.
+    #[arg(long)]
+    pty: bool,
+
+    /// Maximum number of bytes of output captured from each of a test's
+    /// `stdout` and `stderr`, 0 for no limit.
+    #[arg(long, value_name = "BYTES", default_value_t = 1024 * 1024)]
+    max_output: usize,
+
+    /// Maximum number of bytes of output buffered by all the tests running in
+    /// parallel, above which no new test starts until some finish. 0 for no
+    /// limit.
+    #[arg(long, value_name = "BYTES", default_value_t = 0)]
+    max_concurrent_output_bytes: usize,
+
+    /// Truncates lines of test output longer than this many characters, 0 for
+    /// no limit.
+    #[arg(long, value_name = "CHARS", default_value_t = 500)]
+    max_line_length: usize,
+
+    /// Skips the remaining optional tests once every mandatory test has
+    /// passed, for faster runs. Skipped tests do not count towards the score.
+    #[arg(long)]
+    skip_trailing_optional: bool,
+
+    /// Skips every optional test, for quicker local runs. Skipped tests do not
+    /// count towards the score.
+    #[arg(
+        long,
+        env = "DOTCODESCHOOL_SKIP_OPTIONAL",
+        value_parser = BoolishValueParser::new()
+    )]
+    skip_optional: bool,
+
+    /// Counts mandatory tests which are skipped, such as by their `skip_if`
+    /// guard or a failed prerequisite, as failed. For strict grading.
+    #[arg(long, conflicts_with = "start_at")]
+    fail_on_skip: bool,
+
+    /// Shuffles the tests of each suite in an order derived from this string,
+    /// such as a student ID, so that each student gets their own order but
+    /// always the same one. Tests with an explicit `order` stay in place.
+    #[arg(long, value_name = "SEED")]
+    exam_seed: Option<String>,
+
+    /// Computes the score over mandatory tests only, optional tests which
+    /// passed being reported separately as a bonus. By default, optional
+    /// tests count towards the score like mandatory ones.
+    #[arg(long)]
+    no_optional_in_score: bool,
+
+    /// Continues the last run if it was interrupted, keeping the status of the
+    /// tests it finished and running the others.
+    #[arg(long)]
+    r#continue: bool,
+
+    /// Loads the course as this format version, ignoring its `version` field.
+    /// Meant for testing new course formats against existing files.
+    #[arg(long, value_name = "VERSION")]
+    force_version: Option<String>,
+
+    /// Rejects courses containing unknown fields, such as misspelled ones,
+    /// instead of ignoring them, as well as courses in which suites or tests
+    /// share a name or which use environment variables that are not set with
+    /// `expand_env`, instead of warning about them.
+    #[arg(long, global = true)]
+    strict_json: bool,
+
+    /// Prints a JSON Schema of course files, for editor validation.
+    #[arg(long)]
+    schema: bool,
+
+    /// Runs the course this many times, reloading the course file before each
+    /// run so that changes to it are picked up.
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    repeat: u32,
+
+    /// Only runs the tests with this name.
+    #[arg(long, value_name = "NAME")]
+    test: Option<String>,
+
+    /// Starts the run at the suite with this name or position, starting from
+    /// 1. Earlier suites are skipped and left out of the score.
+    #[arg(long, value_name = "SUITE")]
+    start_at: Option<String>,
+
+    /// Highlights the tests added in this version of the course or later,
+    /// according to their `added_in` field.
+    #[arg(long, value_name = "VERSION", value_parser = parse_course_version)]
+    highlight_new: Option<String>,
+
+    /// Runs the selected test again and again until it passes, clearing the
+    /// screen between runs. Stop with Ctrl-C.
+    #[arg(long, requires = "test", conflicts_with = "repeat")]
+    until_pass: bool,
+
+    /// Prints the state machine of the test runner as a Graphviz DOT graph.
+    #[arg(long, hide = true)]
+    #[serde(skip)]
+    print_state_diagram: bool,
+
+    /// Milliseconds to wait after asking a timed out test to terminate before
+    /// killing it. Only graceful on Unix.
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    timeout_kill_grace: u64,
+
+    /// Extra arguments appended to the commands of every test, such as
+    /// `-- --nocapture`.
+    #[arg(last = true, value_name = "ARGS")]
+    #[serde(skip)]
+    args: Vec<String>,
+
+    /// Prints the settings resulting from the config file and command line
+    /// flags, in the format of config files, and exits.
+    #[arg(long)]
+    #[serde(skip)]
+    show_config: bool,
 }
 
-fn main() {
-    let args = Args::parse();
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Writes a starter `tests.json` to the current directory.
+    Init {
+        /// Overwrites any existing `tests.json`.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Checks the course for mistakes without running any test, exiting with
+    /// an error if any are found.
+    Validate,
+    /// Runs again only the tests which failed or were not run during the
+    /// last run, keeping the status of the others.
+    RetryFailed,
+}
 
-    Builder::from_default_env()
+/// Parses the command line, using the settings of the config file as defaults.
+///
+/// Settings only apply to flags which were neither passed on the command line
+/// nor through the environment, and which do not conflict with one that was,
+/// so that `--buffered` still wins over `stream = true` in the config file.
+fn parse_args() -> Args {
+    // Only the config file is needed at first, the command line being checked
+    // once the flags it sets are known
+    let config = Args::command()
+        .ignore_errors(true)
+        .get_matches()
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(find_config);
+    let settings = match config.map(|path| load_config(&path)) {
+        Some(Ok(settings)) => settings,
+        // The config file only holds default flags, so an invalid one is
+        // reported like invalid arguments
+        Some(Err(err)) => {
+            Args::command().error(ErrorKind::InvalidValue, err).exit()
+        }
+        None => Vec::new(),
+    };
+
+    let mut command = Args::command();
+    let mut defaults = Vec::new();
+    for (flag, values) in settings {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(flag.as_str()));
+        let Some(arg) = arg else {
+            let msg = format!("unknown flag '{flag}' in config file");
+            command.error(ErrorKind::UnknownArgument, msg).exit()
+        };
+        let (arg, is_flag) =
+            (arg.clone(), matches!(arg.get_action(), ArgAction::SetTrue));
+
+        // Boolean flags enabled by the config file can be turned off again
+        if is_flag {
+            command = command.mut_arg(arg.get_id(), |arg| {
+                arg.action(ArgAction::Set)
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_value("false")
+                    .default_missing_value("true")
+                    .value_parser(BoolishValueParser::new())
+            });
+        }
+        defaults.push((arg, values));
+    }
+
+    let matches = command.clone().get_matches();
+    let is_set = |id: &Id| {
+        !matches!(
+            matches.value_source(id.as_str()),
+            None | Some(ValueSource::DefaultValue)
+        )
+    };
+    for (arg, values) in defaults {
+        let conflicts_with = |a: &Arg, b: &Arg| {
+            command
+                .get_arg_conflicts_with(a)
+                .iter()
+                .any(|conflict| conflict.get_id() == b.get_id())
+        };
+        let conflicts = command.get_arguments().filter(|other| {
+            conflicts_with(&arg, other) || conflicts_with(other, &arg)
+        });
+        if is_set(arg.get_id()) || conflicts.map(Arg::get_id).any(is_set) {
+            continue;
+        }
+        command =
+            command.mut_arg(arg.get_id(), |arg| arg.default_values(values));
+    }
+
+    Args::from_arg_matches(&command.get_matches())
+        .unwrap_or_else(|err| err.exit())
+}
+
+fn main() {
+    // Warnings are shown by default as they report problems which do not
+    // stop the run, such as course files being skipped
+    Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .parse_default_env()
         .format(|buf, record| {
             writeln!(
                 buf,
@@ -29,13 +438,373 @@ fn main() {
         })
         .init();
 
-    let path = match args.tests {
-        Some(path) => path,
-        None => "./tests.json".to_string(),
+    let args = parse_args();
+
+    if args.show_config {
+        // The locale is displayed as it was resolved from the environment
+        let args = Args {
+            locale: args.locale.or_else(|| Some(Locale::from_env())),
+            ..args
+        };
+        let settings = serde_json::to_value(&args)
+            .expect("settings should always serialize");
+        print!("{}", to_config(&settings));
+        return;
+    }
+
+    // Messages displayed before the course is run, which uses its options
+    let msg = args.locale.unwrap_or_else(Locale::from_env).catalog();
+    let glyphs = Glyphs::select(args.ascii);
+
+    if let Some(Command::Init { force }) = args.command {
+        init(force, msg, glyphs);
+        return;
+    }
+
+    if args.schema {
+        let schema = serde_json::to_string_pretty(&JsonCourseV1::schema())
+            .expect("course schema should always serialize");
+        println!("{schema}");
+        return;
+    }
+
+    if let Some(path) = &args.replay {
+        if let Err(err) = recording::replay(path) {
+            log::error!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.print_state_diagram {
+        print!("{}", TestRunnerState::to_dot());
+        return;
+    }
+
+    // Resolved before moving to the course so that paths, whether passed on
+    // the command line or set in the config file, stay relative to where the
+    // CLI was run from
+    let absolute = |path: PathBuf| std::path::absolute(&path).unwrap_or(path);
+    let args = Args {
+        root: args.root.map(absolute),
+        fixtures: args.fixtures.map(absolute),
+        record: args.record.map(absolute),
+        markdown: args.markdown.map(absolute),
+        badge: args.badge.map(absolute),
+        summary_json_path: args.summary_json_path.map(absolute),
+        ..args
+    };
+
+    let source = match (args.tests_dir, args.tests) {
+        (Some(dir), _) => CourseSource::Dir(dir),
+        (None, Some(path)) if Path::new(&path).is_dir() => {
+            CourseSource::Split(path)
+        }
+        (None, Some(path)) => CourseSource::File(path),
+        (None, None) => {
+            // Commands are written relative to the course file, so the run
+            // happens where it was found
+            match find_tests() {
+                Some(dir) => {
+                    log::info!("Using the course in {}", dir.display());
+                    if let Err(err) = std::env::set_current_dir(&dir) {
+                        log::error!(
+                            "failed to move to {}: {err}",
+                            dir.display()
+                        );
+                        std::process::exit(1);
+                    }
+                }
+                // Most likely a first run, which deserves guidance rather
+                // than a load error
+                None if !Path::new(DEFAULT_TESTS).is_file() => {
+                    eprintln!("{}", msg.no_course);
+                    std::process::exit(ExitReason::NoCourse.code());
+                }
+                None => {}
+            }
+            CourseSource::File(DEFAULT_TESTS.to_string())
+        }
+    };
+
+    if let Some(Command::Validate) = args.command {
+        validate(
+            &source,
+            &LoadOptions {
+                force_version: args.force_version.as_deref(),
+                strict: args.strict_json,
+            },
+            msg,
+            glyphs,
+        );
+        return;
+    }
+
+    // Failing to find the last run is an error rather than a full run, as
+    // students would otherwise wait for every test to run again
+    let retry = match args.command {
+        Some(Command::RetryFailed) => {
+            match RunState::load(std::path::Path::new(STATE_FILE)) {
+                Ok(state) => Some(state),
+                Err(err) => {
+                    log::debug!("{err}");
+                    let locale = args.locale.unwrap_or_else(Locale::from_env);
+                    eprintln!("{}", locale.catalog().no_previous_run);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Runs which went all the way to their end have nothing left to continue
+    let resume = match args.r#continue {
+        true => RunState::load(std::path::Path::new(STATE_FILE))
+            .map_err(|err| log::debug!("{err}"))
+            .ok()
+            .filter(|state| !state.finished),
+        false => None,
+    };
+    if args.r#continue && resume.is_none() {
+        log::info!("No interrupted run to continue, running every test");
+    }
+
+    let options = RunnerOptions {
+        locale: args.locale.unwrap_or_else(Locale::from_env),
+        score_only: args.print_score_only,
+        json: args.json,
+        ascii: args.ascii,
+        theme: args.theme,
+        stream: args.stream,
+        kill_grace: Duration::from_millis(args.timeout_kill_grace),
+        max_output: Some(args.max_output).filter(|max| *max > 0),
+        max_concurrent_output: Some(args.max_concurrent_output_bytes)
+            .filter(|max| *max > 0),
+        max_line_length: args.max_line_length,
+        markdown: args.markdown,
+        badge: args.badge,
+        summary_json_path: args.summary_json_path,
+        buffered: args.buffered,
+        compact: args.compact,
+        color_passthrough: args.color_output_passthrough,
+        no_progress: args.no_progress,
+        skip_trailing_optional: args.skip_trailing_optional,
+        skip_optional: args.skip_optional,
+        exam_seed: args.exam_seed,
+        no_optional_in_score: args.no_optional_in_score,
+        fail_on_skip: args.fail_on_skip,
+        retry,
+        resume,
+        state_path: Some(STATE_FILE.into()),
+        force_version: args.force_version,
+        strict_json: args.strict_json,
+        jobs: args.jobs,
+        jobs_suite: args.jobs_suite,
+        fixtures: args.fixtures,
+        allowed_cmds: Some(args.allow_cmds).filter(|cmds| !cmds.is_empty()),
+        strict_perf: args.strict_perf,
+        profile: args.profile,
+        record: args.record,
+        root: args.root,
+        merge_output: args.merge_output,
+        diff_context: args.diff_context,
+        pty: args.pty,
+        strict_toolchain: args.strict_toolchain,
+        preflight: args.preflight,
+        title_progress: args.title_progress,
+        start_at: args.start_at.clone(),
+        highlight_new: args.highlight_new,
+        explain_scoring: args.explain_scoring,
+        args: args.args,
+        group_by: args.group_by,
     };
 
-    let mut runner = RunnerVersion::new(&path);
-    while runner.state() != TestRunnerState::Finish {
-        runner = runner.run();
+    // The course is loaded again before each run, the last course which
+    // loaded successfully being run if it has since become invalid
+    let msg = options.locale.catalog();
+    let mut course = None;
+    let mut reason = ExitReason::Passed;
+    for run in 1.. {
+        course = match (source.load_with(&options.load_options()), course) {
+            (Ok(loaded), _) => Some(loaded),
+            (Err(err), Some(previous)) => {
+                report_load_error(&err, msg.reload_failed, &options);
+                Some(previous)
+            }
+            (Err(err), None) => {
+                report_load_error(&err, msg.load_failed, &options);
+                std::process::exit(ExitReason::LoadFailed.code());
+            }
+        };
+
+        let selected = match (&course, &args.test) {
+            (Some(JsonCourseVersion::V1(course)), Some(name)) => {
+                match course.only_test(name) {
+                    Some(course) => Some(JsonCourseVersion::V1(course)),
+                    None => {
+                        log::error!("no test named '{name}'");
+                        std::process::exit(ExitReason::LoadFailed.code());
+                    }
+                }
+            }
+            (course, _) => course.clone(),
+        };
+
+        if let (Some(JsonCourseVersion::V1(course)), Some(suite)) =
+            (&selected, &args.start_at)
+        {
+            if course.suite_index(suite).is_none() {
+                log::error!("no suite named '{suite}'");
+                std::process::exit(ExitReason::LoadFailed.code());
+            }
+        }
+
+        // Machine readable output must not be mixed with escape sequences
+        let interactive = std::io::stdout().is_terminal()
+            && !args.json
+            && !args.print_score_only;
+        if args.until_pass && run > 1 && interactive {
+            print!("\x1b[2J\x1b[H");
+            let _ = std::io::stdout().flush();
+        }
+
+        let mut runner = match selected {
+            Some(course) => RunnerVersion::from_course(course, options.clone()),
+            None => RunnerVersion::Undefined,
+        };
+        while runner.state() != TestRunnerState::Finish {
+            runner = runner.run();
+        }
+
+        if args.until_pass {
+            match runner.exit_reason() {
+                ExitReason::Passed => break,
+                _ => {
+                    thread::sleep(UNTIL_PASS_DELAY);
+                    continue;
+                }
+            }
+        }
+
+        // A failure in any of the runs is kept when repeating
+        if runner.exit_reason() != ExitReason::Passed {
+            reason = runner.exit_reason();
+        }
+        if run >= args.repeat {
+            break;
+        }
+    }
+
+    std::process::exit(reason.code());
+}
+
+/// Checks that a command line argument is a version such as `1.2`.
+///
+/// * `version`: argument to check.
+fn parse_course_version(version: &str) -> Result<String, String> {
+    match parse_version(version) {
+        Some(_) => Ok(version.to_string()),
+        None => Err("expected a version such as 1.2".to_string()),
+    }
+}
+
+/// Loads a course and reports every problem found in it, exiting with an
+/// error if there are any.
+///
+/// * `source`: where to load the course from.
+/// * `opts`: settings affecting how the course is loaded.
+/// * `msg`: messages in the language of the user.
+/// * `glyphs`: markers to decorate output with.
+fn validate(
+    source: &CourseSource,
+    opts: &LoadOptions,
+    msg: &Catalog,
+    glyphs: &Glyphs,
+) {
+    let problems = match source.load_with(opts) {
+        Ok(JsonCourseVersion::V1(course)) => {
+            course.validate().iter().map(ToString::to_string).collect()
+        }
+        Err(err) => vec![err.to_string()],
+    };
+
+    if problems.is_empty() {
+        println!("{} {}", glyphs.pass, msg.course_valid);
+        return;
     }
+
+    println!(
+        "{} {}",
+        glyphs.fail,
+        Catalog::format(
+            msg.course_problems,
+            "count",
+            &problems.len().to_string()
+        )
+    );
+    for problem in problems {
+        println!("    - {problem}");
+    }
+    std::process::exit(1);
+}
+
+/// Looks for [DEFAULT_TESTS] in the parents of the current directory, like git
+/// looks for `.git`, for students running the CLI from a subdirectory.
+///
+/// The search stops at the root of the git repository so that the course of an
+/// unrelated project is never picked up. Returns the directory containing the
+/// course file, or [None] if it is in the current directory or was not found.
+fn find_tests() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+
+    for dir in cwd.ancestors() {
+        if dir.join(DEFAULT_TESTS).is_file() {
+            return Some(dir.to_path_buf()).filter(|dir| *dir != cwd);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    None
+}
+
+/// Scaffolds a new course file at [DEFAULT_TESTS].
+///
+/// The generated course is a valid V1 course with one suite and one test,
+/// exercising every field so authors only have to fill in the blanks.
+///
+/// * `force`: overwrite the course file if it already exists.
+/// * `msg`: messages in the language of the user.
+/// * `glyphs`: markers to decorate output with.
+fn init(force: bool, msg: &Catalog, glyphs: &Glyphs) {
+    if !force && std::path::Path::new(DEFAULT_TESTS).exists() {
+        log::error!(
+            "{}",
+            Catalog::format(msg.course_exists, "path", DEFAULT_TESTS)
+        );
+        std::process::exit(1);
+    }
+
+    let template = serde_json::to_string_pretty(&JsonCourseV1::template())
+        .expect("course template should always serialize");
+
+    if let Err(err) = std::fs::write(DEFAULT_TESTS, template + "\n") {
+        log::error!(
+            "{}",
+            Catalog::format(
+                &Catalog::format(msg.write_failed, "path", DEFAULT_TESTS),
+                "err",
+                &err.to_string()
+            )
+        );
+        std::process::exit(1);
+    }
+
+    println!(
+        "{} {}",
+        glyphs.updated,
+        Catalog::format(msg.course_created, "path", DEFAULT_TESTS)
+    );
+    println!("\n{}", msg.course_guide);
 }
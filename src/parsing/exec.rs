@@ -0,0 +1,771 @@
+//! A module for executing the commands which make up a test.
+//!
+//! Commands are split on whitespace, the first word being the binary to run
+//! and the rest its arguments. By default the output of a command is buffered
+//! until it exits, but it can also be streamed line by line as it is produced
+//! and commands can be killed if they run for too long (see [ExecContext]).
+//! On Unix, the resources used by commands are also measured once they exit.
+
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read},
+    path::Path,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use thiserror::Error;
+
+/// How often a running command is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    static ref ENV_VAR: Regex =
+        Regex::new(r"\$\$|\$\{(\w+)\}|\$([A-Za-z_]\w*)").unwrap();
+}
+
+#[derive(Error, Debug)]
+pub enum ExecError {
+    #[error("could not execute '{0}'")]
+    SpawnError(String),
+    #[error("could not wait for '{0}' to exit: {1}")]
+    WaitError(String, io::Error),
+    #[error("'{0}' timed out after {1:?}")]
+    Timeout(String, Duration),
+    #[error("command not permitted: '{0}'")]
+    NotPermitted(String),
+}
+
+/// Resources used by a command, as reported by the operating system. Only
+/// measured on Unix.
+///
+/// * `max_rss_kb`: peak resident memory, in kilobytes.
+/// * `user_ms`: CPU time spent running the command's own code, in milliseconds.
+/// * `system_ms`: CPU time spent by the kernel on behalf of the command, in
+///   milliseconds.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceUsage {
+    pub max_rss_kb: u64,
+    pub user_ms: f64,
+    pub system_ms: f64,
+}
+
+impl ResourceUsage {
+    /// Combines the usage of two commands run one after the other, adding up
+    /// their CPU time and keeping the highest peak memory.
+    ///
+    /// * `other`: usage of the other command.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            max_rss_kb: self.max_rss_kb.max(other.max_rss_kb),
+            user_ms: self.user_ms + other.user_ms,
+            system_ms: self.system_ms + other.system_ms,
+        }
+    }
+}
+
+/// Settings affecting how the commands of a test are executed.
+///
+/// * `on_line`: called with every line of `stdout` and `stderr` as it is
+///   produced. Output is only buffered until the command exits if this is not
+///   set.
+/// * `timeout`: how long a command can run before it is terminated.
+/// * `kill_grace`: how long to wait after asking a command to terminate before
+///   killing it forcefully.
+/// * `interpreter`: program to run commands with, such as `python3`. Commands
+///   are passed to it as arguments instead of their first word being run.
+/// * `max_output`: maximum number of bytes captured from each of `stdout` and
+///   `stderr`, so that commands printing enormous output do not exhaust the
+///   memory of the runner.
+/// * `on_usage`: called with the resources used by a command once it exits.
+///   Never called outside of Unix or for commands which timed out.
+/// * `on_timeout`: called when a command is terminated for exceeding its
+///   timeout.
+/// * `on_retry`: called when a test which failed is about to be run again.
+/// * `on_background`: called with every command started in the background by
+///   a test, which is then responsible for stopping it, see [spawn].
+/// * `on_output`: called with the number of bytes of `stdout` and `stderr`
+///   captured as they are read, so that the memory used by output can be
+///   followed while commands run.
+/// * `allowed`: binaries which commands are allowed to run, compared with the
+///   first word of the command as written. Any binary is allowed if [None].
+/// * `root`: directory which the `working_dir` of tests is relative to, the
+///   current directory if [None].
+/// * `working_dir`: directory commands are run in, the current directory if
+///   [None].
+/// * `args`: extra arguments appended to every command, such as those passed
+///   after `--` on the command line.
+/// * `merge_output`: redirects `stderr` into `stdout`, so that the output of
+///   commands is captured in the order in which it was written. Both are then
+///   set to the combined output.
+/// * `diff_context`: number of unchanged lines displayed around each change
+///   when the output of a test differs from its `expected_stdout`.
+/// * `expand_env`: replaces `$VAR` and `${VAR}` in commands with the value of
+///   the environment variable before splitting them, see [expand_vars].
+/// * `pty`: runs commands attached to a pseudo-terminal, so that tools which
+///   only use colors in a terminal still do. Like with `merge_output`, both
+///   `stdout` and `stderr` are then set to the combined output. Only
+///   supported on Unix, commands being run with pipes elsewhere.
+#[derive(Default, Clone, Copy)]
+pub struct ExecContext<'a> {
+    pub on_line: Option<&'a (dyn Fn(&str) + Sync)>,
+    pub on_usage: Option<&'a (dyn Fn(ResourceUsage) + Sync)>,
+    pub on_timeout: Option<&'a (dyn Fn() + Sync)>,
+    pub on_retry: Option<&'a (dyn Fn() + Sync)>,
+    pub on_output: Option<&'a (dyn Fn(usize) + Sync)>,
+    pub on_background: Option<&'a (dyn Fn(Child) + Sync)>,
+    pub allowed: Option<&'a [String]>,
+    pub timeout: Option<Duration>,
+    pub kill_grace: Duration,
+    pub interpreter: Option<&'a str>,
+    pub max_output: Option<usize>,
+    pub root: Option<&'a Path>,
+    pub working_dir: Option<&'a Path>,
+    pub args: &'a [String],
+    pub merge_output: bool,
+    pub diff_context: usize,
+    pub pty: bool,
+    pub expand_env: bool,
+}
+
+/// Splits a command on whitespace into the process to run, without starting
+/// it. Its input is closed and its output piped.
+///
+/// * `cmd`: command to run, the first word being the binary unless an
+///   interpreter is set.
+/// * `ctx`: settings affecting how the command is executed.
+fn command(cmd: &str, ctx: &ExecContext) -> Result<Command, ExecError> {
+    let spawn_error = || ExecError::SpawnError(cmd.to_string());
+    let expanded = ctx.expand_env.then(|| {
+        expand_vars(cmd, &mut |var| {
+            log::warn!("'{var}' is not set, expanding it to nothing in '{cmd}'")
+        })
+    });
+    let words: Vec<&str> = ctx
+        .interpreter
+        .into_iter()
+        .chain([expanded.as_deref().unwrap_or(cmd)])
+        .flat_map(str::split_whitespace)
+        .chain(ctx.args.iter().map(String::as_str))
+        .collect();
+
+    let binary = words.first().ok_or_else(spawn_error)?;
+
+    // Binaries are compared as written rather than resolved, so that a
+    // `./cargo` script in the repository does not pass for `cargo`
+    if ctx.allowed.is_some_and(|allowed| !allowed.iter().any(|b| b == binary)) {
+        log::warn!("Refusing to run '{cmd}', '{binary}' is not allowed");
+        return Err(ExecError::NotPermitted(binary.to_string()));
+    }
+
+    let mut command = Command::new(binary);
+    command
+        .args(words[1..].iter())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(dir) = ctx.working_dir {
+        command.current_dir(dir);
+    }
+
+    Ok(command)
+}
+
+/// Splits a command on whitespace and executes it, waiting for it to finish.
+///
+/// `stdout` and `stderr` are captured separately so that the result can be
+/// displayed depending on whether the command succeeded.
+///
+/// * `cmd`: command to execute, the first word being the binary unless an
+///   interpreter is set.
+/// * `ctx`: settings affecting how the command is executed.
+pub fn execute(cmd: &str, ctx: &ExecContext) -> Result<Output, ExecError> {
+    let spawn_error = || ExecError::SpawnError(cmd.to_string());
+    let mut command = command(cmd, ctx)?;
+
+    // Commands which can time out are run in their own process group so that
+    // any process they spawn is terminated along with them
+    #[cfg(unix)]
+    if ctx.timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    // Both streams share a single pipe when merged so that lines are read in
+    // the order in which they were written, like with `2>&1`
+    let merged: Option<Box<dyn Read + Send>> =
+        match (pty(ctx), ctx.merge_output) {
+            (true, _) => {
+                let (reader, writer) = open_pty().map_err(|err| {
+                    log::warn!("failed to open a pseudo-terminal: {err}");
+                    spawn_error()
+                })?;
+                let stderr = writer.try_clone().map_err(|_| spawn_error())?;
+                command.stdout(writer).stderr(stderr);
+                Some(Box::new(reader))
+            }
+            (false, true) => {
+                let (reader, writer) = io::pipe().map_err(|_| spawn_error())?;
+                let stderr = writer.try_clone().map_err(|_| spawn_error())?;
+                command.stdout(writer).stderr(stderr);
+                Some(Box::new(reader))
+            }
+            (false, false) => None,
+        };
+
+    let mut child = command.spawn().map_err(|_| spawn_error())?;
+    // The command holds the write end of the merged pipe, which must be closed
+    // for reading it to ever finish
+    drop(command);
+
+    let (stdout, stderr): (Box<dyn Read + Send>, _) = match merged {
+        Some(reader) => (reader, None),
+        None => (
+            Box::new(child.stdout.take().ok_or_else(spawn_error)?),
+            Some(child.stderr.take().ok_or_else(spawn_error)?),
+        ),
+    };
+
+    let (on_line, on_output, limit) =
+        (ctx.on_line, ctx.on_output, ctx.max_output);
+    let (status, stdout, stderr) = thread::scope(|s| {
+        let stdout =
+            s.spawn(move || read_lines(stdout, on_line, on_output, limit));
+        let stderr = s.spawn(move || {
+            stderr.map(|stderr| read_lines(stderr, on_line, on_output, limit))
+        });
+        let status = wait(&mut child, ctx);
+        let stdout = stdout.join().unwrap();
+        let stderr = stderr.join().unwrap().unwrap_or_else(|| stdout.clone());
+        (status, stdout, stderr)
+    });
+
+    match status {
+        Ok(Some(status)) => Ok(Output { status, stdout, stderr }),
+        Ok(None) => Err(ExecError::Timeout(
+            cmd.to_string(),
+            ctx.timeout.unwrap_or_default(),
+        )),
+        Err(err) => Err(ExecError::WaitError(cmd.to_string(), err)),
+    }
+}
+
+/// Replaces environment variables written as `$VAR` or `${VAR}` with their
+/// value, like a shell would. `$$` stands for a literal `$`.
+///
+/// * `text`: text to expand, such as a command.
+/// * `on_undefined`: called with the name of every variable which is not set,
+///   such variables expanding to nothing.
+pub fn expand_vars(text: &str, on_undefined: &mut dyn FnMut(&str)) -> String {
+    ENV_VAR
+        .replace_all(text, |caps: &Captures| {
+            let Some(var) = caps.get(1).or(caps.get(2)) else {
+                return "$".to_string();
+            };
+            std::env::var(var.as_str()).unwrap_or_else(|_| {
+                on_undefined(var.as_str());
+                String::new()
+            })
+        })
+        .to_string()
+}
+
+/// Starts a command in the background without waiting for it, such as a
+/// server which later tests connect to. Its output is discarded.
+///
+/// Like commands with a timeout, the command is run in its own process group
+/// so that any process it spawns, such as the binary started by `cargo run`,
+/// is stopped along with it, see [stop].
+///
+/// * `cmd`: command to start, the first word being the binary unless an
+///   interpreter is set.
+/// * `ctx`: settings affecting how the command is executed.
+pub fn spawn(cmd: &str, ctx: &ExecContext) -> Result<Child, ExecError> {
+    let mut command = command(cmd, ctx)?;
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let child =
+        command.spawn().map_err(|_| ExecError::SpawnError(cmd.to_string()))?;
+    #[cfg(unix)]
+    signals::track(child.id());
+
+    Ok(child)
+}
+
+/// Stops a command started with [spawn] along with its process group, asking
+/// them to terminate with `SIGTERM` before killing them if they are still
+/// running after `grace`.
+///
+/// * `child`: command to stop.
+/// * `grace`: how long to wait before killing the command.
+pub fn stop(child: &mut Child, grace: Duration) {
+    log::debug!("Stopping background process {}", child.id());
+    terminate(child, grace);
+    #[cfg(unix)]
+    signals::untrack(child.id());
+}
+
+/// Commands started with [spawn], stopped with [stop] once dropped so that
+/// they never outlive whoever started them.
+///
+/// * `children`: commands which have not been stopped yet.
+/// * `grace`: how long to wait before killing each command.
+pub struct Background {
+    children: Vec<Child>,
+    grace: Duration,
+}
+
+impl Background {
+    /// Holds no command yet.
+    ///
+    /// * `grace`: how long to wait before killing each command once stopped.
+    pub fn new(grace: Duration) -> Self {
+        Self { children: Vec::new(), grace }
+    }
+
+    /// Stops every command held so far.
+    pub fn stop(&mut self) {
+        for mut child in self.children.drain(..) {
+            stop(&mut child, self.grace);
+        }
+    }
+}
+
+impl Extend<Child> for Background {
+    fn extend<I: IntoIterator<Item = Child>>(&mut self, children: I) {
+        self.children.extend(children);
+    }
+}
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Forwards `SIGINT` and `SIGTERM` to the process groups of the commands
+/// started with [spawn], which do not receive the `Ctrl-C` of the terminal
+/// as they are not part of its foreground group.
+#[cfg(unix)]
+mod signals {
+    use std::sync::{
+        atomic::{AtomicI32, Ordering},
+        Once,
+    };
+
+    /// Process groups of the commands which are still running, 0 marking a
+    /// free slot. Their number is fixed as the signal handler reading them
+    /// must neither allocate nor lock.
+    static GROUPS: [AtomicI32; 64] = [const { AtomicI32::new(0) }; 64];
+
+    static INSTALL: Once = Once::new();
+
+    /// Forwards signals to the process group of a command until it is
+    /// untracked.
+    ///
+    /// * `pid`: id of the command, which leads its own process group.
+    pub fn track(pid: u32) {
+        INSTALL.call_once(|| {
+            for signal in [libc::SIGINT, libc::SIGTERM] {
+                // SAFETY: `forward` only calls async-signal-safe functions
+                unsafe {
+                    let handler = forward as extern "C" fn(_) as usize;
+                    let previous = libc::signal(signal, handler);
+                    // Signals which were ignored or already handled are left
+                    // as they were
+                    if previous != libc::SIG_DFL {
+                        libc::signal(signal, previous);
+                    }
+                }
+            }
+        });
+
+        let tracked = GROUPS.iter().any(|group| {
+            group
+                .compare_exchange(
+                    0,
+                    pid as i32,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                )
+                .is_ok()
+        });
+        if !tracked {
+            log::warn!(
+                "too many background processes, {pid} will not be stopped if \
+                 the run is interrupted"
+            );
+        }
+    }
+
+    /// Stops forwarding signals to the process group of a command.
+    ///
+    /// * `pid`: id of the command.
+    pub fn untrack(pid: u32) {
+        for group in &GROUPS {
+            let _ = group.compare_exchange(
+                pid as i32,
+                0,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    /// Sends `signal` to every tracked process group, then to the runner
+    /// itself as if it had not been handled.
+    ///
+    /// * `signal`: signal which was received.
+    extern "C" fn forward(signal: libc::c_int) {
+        for group in &GROUPS {
+            let group = group.load(Ordering::SeqCst);
+            if group != 0 {
+                // SAFETY: `kill` is async-signal-safe
+                unsafe { libc::kill(-group, signal) };
+            }
+        }
+
+        // SAFETY: both are async-signal-safe
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+/// Checks whether the binary of a command can be found, without executing it.
+///
+/// Binaries containing a path separator are looked up relative to the current
+/// directory, others are looked up in every directory of `PATH`.
+///
+/// * `cmd`: command to resolve, the first word being the binary.
+pub fn resolve(cmd: &str) -> bool {
+    let binary = match cmd.split_whitespace().next() {
+        Some(binary) => Path::new(binary),
+        None => return false,
+    };
+
+    if binary.components().count() > 1 {
+        return is_executable(binary);
+    }
+
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths)
+            .any(|dir| is_executable(&dir.join(binary)))
+    })
+}
+
+/// Checks whether a file exists and can be executed.
+///
+/// * `path`: path to the file.
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.is_file() || path.with_extension("exe").is_file()
+    }
+}
+
+/// Whether commands are run attached to a pseudo-terminal, see
+/// [ExecContext::pty].
+///
+/// * `ctx`: settings affecting how the command is executed.
+fn pty(ctx: &ExecContext) -> bool {
+    cfg!(unix) && ctx.pty
+}
+
+/// Opens a pseudo-terminal, returning the end the output of a command is read
+/// from and the end the command writes to.
+///
+/// Newlines are written as is rather than translated to `\r\n`, so that the
+/// output reads the same as through a pipe.
+#[cfg(unix)]
+fn open_pty() -> io::Result<(std::fs::File, std::fs::File)> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let (mut master, mut slave) = (0, 0);
+    // SAFETY: `master` and `slave` are valid for writes, the name, settings
+    // and size of the terminal are optional.
+    let opened = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if opened != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: both descriptors were just opened and are owned by nothing else
+    let (master, slave) = unsafe {
+        (std::fs::File::from_raw_fd(master), std::fs::File::from_raw_fd(slave))
+    };
+
+    // Commands spawned by other tests meanwhile would otherwise inherit the
+    // terminal, keeping it open until they exit
+    for fd in [master.as_raw_fd(), slave.as_raw_fd()] {
+        // SAFETY: `fd` is owned by one of the files above, which are open
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    // SAFETY: `termios` is plain data, for which all zeroes is a valid value,
+    // and is valid for reads and writes for the duration of the calls.
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(slave.as_raw_fd(), &mut termios) == 0 {
+            termios.c_oflag &= !libc::OPOST;
+            libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios);
+        }
+    }
+
+    Ok((master, slave))
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> io::Result<(std::fs::File, std::fs::File)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Waits for a command to exit, terminating it if it exceeds its timeout.
+///
+/// Returns [None] if the command timed out, or an error if its status could
+/// not be collected.
+///
+/// * `child`: running command.
+/// * `ctx`: settings affecting how the command is executed.
+fn wait(
+    child: &mut Child,
+    ctx: &ExecContext,
+) -> io::Result<Option<ExitStatus>> {
+    let start = Instant::now();
+    loop {
+        match reap(child, ctx.timeout.is_none())? {
+            Some((status, usage)) => {
+                if let (Some(on_usage), Some(usage)) = (ctx.on_usage, usage) {
+                    on_usage(usage);
+                }
+                return Ok(Some(status));
+            }
+            None if ctx.timeout.is_some_and(|t| start.elapsed() < t) => {
+                thread::sleep(POLL_INTERVAL)
+            }
+            None => {
+                let timeout = ctx.timeout.unwrap_or_default();
+                log::debug!("Command timed out after {timeout:?}");
+                terminate(child, ctx.kill_grace);
+                if let Some(on_timeout) = ctx.on_timeout {
+                    on_timeout();
+                }
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Collects the exit status of a command along with the resources it used,
+/// returning [None] if it is still running.
+///
+/// The command is waited for with `wait4` rather than through [Child] so that
+/// resource usage is reported too. The [Child] must therefore not be waited
+/// for again once this has returned a status.
+///
+/// * `child`: running command.
+/// * `block`: waits for the command to exit instead of returning immediately.
+#[cfg(unix)]
+fn reap(
+    child: &mut Child,
+    block: bool,
+) -> std::io::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let options = if block { 0 } else { libc::WNOHANG };
+    let mut status = 0;
+    // SAFETY: `rusage` is plain data, for which all zeroes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    loop {
+        // SAFETY: `status` and `usage` are valid for writes for the duration
+        // of the call.
+        let pid = unsafe {
+            libc::wait4(child.id() as i32, &mut status, options, &mut usage)
+        };
+
+        match pid {
+            0 => return Ok(None),
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    // Peak memory is reported in bytes on macOS and kilobytes elsewhere
+    let max_rss = usage.ru_maxrss as u64;
+    let max_rss_kb = match cfg!(target_os = "macos") {
+        true => max_rss / 1024,
+        false => max_rss,
+    };
+    let millis = |time: libc::timeval| {
+        time.tv_sec as f64 * 1000.0 + time.tv_usec as f64 / 1000.0
+    };
+
+    let usage = ResourceUsage {
+        max_rss_kb,
+        user_ms: millis(usage.ru_utime),
+        system_ms: millis(usage.ru_stime),
+    };
+
+    Ok(Some((ExitStatus::from_raw(status), Some(usage))))
+}
+
+/// Collects the exit status of a command, returning [None] if it is still
+/// running. Resource usage is not measured outside of Unix.
+///
+/// * `child`: running command.
+/// * `block`: waits for the command to exit instead of returning immediately.
+#[cfg(not(unix))]
+fn reap(
+    child: &mut Child,
+    block: bool,
+) -> std::io::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+    let status = match block {
+        true => child.wait().map(Some),
+        false => child.try_wait(),
+    };
+
+    status.map(|status| status.map(|status| (status, None)))
+}
+
+/// Asks a command and its process group to terminate with `SIGTERM`, then
+/// kills them with `SIGKILL` if they are still alive after `grace`.
+///
+/// * `child`: running command.
+/// * `grace`: how long to wait before killing the command.
+#[cfg(unix)]
+fn terminate(child: &mut Child, grace: Duration) {
+    let group = -(child.id() as i32);
+
+    // SAFETY: `kill` has no memory safety requirements, the worst that can
+    // happen is signaling a process group which has already exited.
+    unsafe { libc::kill(group, libc::SIGTERM) };
+
+    let start = Instant::now();
+    while start.elapsed() < grace {
+        if let Ok(Some(_)) = child.try_wait() {
+            break;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // Also kills any process left in the group, even if the command has
+    // already exited, so that they do not keep its output pipes open
+    unsafe { libc::kill(group, libc::SIGKILL) };
+    let _ = child.wait();
+}
+
+/// Kills a command. There is no graceful termination outside of Unix.
+///
+/// * `child`: running command.
+/// * `grace`: ignored.
+#[cfg(not(unix))]
+fn terminate(child: &mut Child, _grace: Duration) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Reads a pipe line by line until it is closed, returning everything read.
+///
+/// The pipe is always read until it is closed so that the command does not
+/// block on a full pipe, but only up to `limit` bytes are kept. A note
+/// mentioning how many bytes were dropped is appended to the output if it was
+/// truncated.
+///
+/// * `pipe`: pipe to read from.
+/// * `on_line`: called with every line read, without its trailing newline.
+///   Lines are truncated to `limit` bytes as well.
+/// * `on_output`: called with the number of bytes kept from every chunk read.
+/// * `limit`: maximum number of bytes to keep, [None] for no limit.
+fn read_lines(
+    pipe: impl Read,
+    on_line: Option<&(dyn Fn(&str) + Sync)>,
+    on_output: Option<&(dyn Fn(usize) + Sync)>,
+    limit: Option<usize>,
+) -> Vec<u8> {
+    let limit = limit.unwrap_or(usize::MAX);
+    let mut reader = BufReader::new(pipe);
+    let mut buffer = Vec::new();
+    let mut line = Vec::new();
+    let mut dropped = 0;
+
+    loop {
+        let chunk = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(chunk) => chunk,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+
+        let keep = chunk.len().min(limit - buffer.len());
+        buffer.extend_from_slice(&chunk[..keep]);
+        dropped += chunk.len() - keep;
+        if let Some(on_output) = on_output.filter(|_| keep > 0) {
+            on_output(keep);
+        }
+
+        if let Some(on_line) = on_line {
+            for piece in chunk.split_inclusive(|byte| *byte == b'\n') {
+                let keep = piece.len().min(limit - line.len().min(limit));
+                line.extend_from_slice(&piece[..keep]);
+
+                if piece.ends_with(b"\n") {
+                    on_line(
+                        String::from_utf8_lossy(&line).trim_end_matches('\n'),
+                    );
+                    line.clear();
+                }
+            }
+        }
+
+        let len = chunk.len();
+        reader.consume(len);
+    }
+
+    if let Some(on_line) = on_line.filter(|_| !line.is_empty()) {
+        on_line(&String::from_utf8_lossy(&line));
+    }
+
+    if dropped > 0 {
+        log::warn!(
+            "Dropped {dropped} bytes of output over the {limit} bytes limit"
+        );
+        buffer.extend_from_slice(
+            format!("\n[... {dropped} bytes truncated]\n").as_bytes(),
+        );
+    }
+
+    buffer
+}
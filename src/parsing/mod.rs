@@ -4,69 +4,543 @@
 //! executing providing an implementation for executing tests. The actual
 //! execution is the responsibility of the test [runner].
 
-use serde_json::Value;
+use std::{collections::BTreeSet, io::ErrorKind, path::Path, sync::Mutex};
+
+use serde::Serialize;
+use serde_json::{error::Category, json, Value};
 use thiserror::Error;
 
-use self::v1::JsonCourseV1;
+use self::{
+    exec::ExecContext,
+    v1::{JsonCourseV1, JsonTestSuiteV1},
+};
 
+pub mod exec;
+#[cfg(test)]
+pub(crate) mod test_utils;
 pub mod v1;
 
 pub const V_1_0: &str = "1.0";
 
+/// Name of the file holding the metadata of a course split into one file per
+/// suite, see [load_course_split].
+pub const COURSE_HEADER: &str = "course.json";
+
+/// Warnings already logged while loading courses, see [warn_once].
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Settings affecting how a course is loaded.
+///
+/// * `force_version`: format version to parse the course as, overriding its
+///   `version` field. Used to test new parsers against existing files.
+/// * `strict`: rejects fields which are not part of the course format, so that
+///   misspelled fields are reported instead of silently ignored, as well as
+///   suites or tests sharing a name and environment variables which are not
+///   set.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LoadOptions<'a> {
+    pub force_version: Option<&'a str>,
+    pub strict: bool,
+}
+
 #[derive(Error, Debug)]
 pub enum ParsingError {
-    #[error("failed to open course file at {0}")]
-    FileOpenError(String),
-    #[error("")]
+    #[error("could not find course file at {path}")]
+    FileNotFound { path: String },
+    #[error("permission denied reading {path}")]
+    PermissionDenied { path: String },
+    #[error("{path} is a directory, not a course file")]
+    IsADirectory { path: String },
+    #[error("failed to open course file at {path}: {source}")]
+    FileOpenError { path: String, source: std::io::Error },
+    #[error("invalid JSON in {path} at line {line}, column {col}: {msg}")]
+    InvalidJson { path: String, line: usize, col: usize, msg: String },
+    #[error("missing field '{field}'{}", location(.suite, .test))]
+    MissingField { suite: Option<String>, test: Option<String>, field: String },
+    #[error("unsupported course version '{found}'")]
+    UnsupportedVersion { found: String },
+    #[error("{0}")]
     CourseFmtError(String),
 }
 
+/// Formats where in a course a [ParsingError] occurred.
+///
+/// * `suite`: name of the suite the error occurred in, if any.
+/// * `test`: name of the test the error occurred in, if any.
+fn location(suite: &Option<String>, test: &Option<String>) -> String {
+    match (suite, test) {
+        (Some(suite), Some(test)) => {
+            format!(" in test '{test}' of suite '{suite}'")
+        }
+        (Some(suite), None) => format!(" in suite '{suite}'"),
+        _ => String::new(),
+    }
+}
+
+impl ParsingError {
+    /// Converts an error which occurred while reading a course file or
+    /// directory into the [ParsingError] describing it most precisely.
+    ///
+    /// * `path`: path which could not be read.
+    /// * `err`: error which occurred.
+    fn io(path: &str, err: std::io::Error) -> Self {
+        let path = path.to_string();
+
+        match err.kind() {
+            ErrorKind::NotFound => Self::FileNotFound { path },
+            ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            ErrorKind::IsADirectory => Self::IsADirectory { path },
+            _ => Self::FileOpenError { path, source: err },
+        }
+    }
+
+    /// Converts a [serde_json::Error] into a [ParsingError::InvalidJson].
+    ///
+    /// * `path`: path to the file which failed to parse.
+    /// * `err`: deserialization error.
+    fn invalid_json(path: &str, err: serde_json::Error) -> Self {
+        // serde_json appends the error position to its message
+        let msg = err.to_string();
+        let position =
+            format!(" at line {} column {}", err.line(), err.column());
+        let msg = msg.strip_suffix(&position).unwrap_or(&msg).to_string();
+
+        Self::InvalidJson {
+            path: path.to_string(),
+            line: err.line(),
+            col: err.column(),
+            msg,
+        }
+    }
+}
+
 pub enum TestResult {
     Pass(String),
     Fail(String),
+    /// A failure with its own message, replacing the message of the test.
+    StepFail {
+        output: String,
+        message: String,
+    },
+    /// A skipped test, along with a description of why it was skipped.
+    Skip(SkipReason, String),
+}
+
+impl TestResult {
+    /// Transforms the output of the test, or the reason it was skipped.
+    ///
+    /// * `f`: transformation to apply.
+    pub fn map_output(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            TestResult::Pass(output) => TestResult::Pass(f(output)),
+            TestResult::Fail(output) => TestResult::Fail(f(output)),
+            TestResult::StepFail { output, message } => {
+                TestResult::StepFail { output: f(output), message }
+            }
+            TestResult::Skip(reason, output) => {
+                TestResult::Skip(reason, f(output))
+            }
+        }
+    }
+}
+
+/// Why a test was skipped, in a form which tools reading the results of a run
+/// can match on.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The `skip_if` guard of the test succeeded.
+    Guard,
+    /// The test is optional and every mandatory test has already passed.
+    MandatoryPassed,
+    /// The test is optional and optional tests are skipped.
+    Optional,
+    /// The test keeps the status it had in the previous run.
+    PreviousRun,
+    /// A test of the suite named in the `requires_suite` of the suite of the
+    /// test failed.
+    PrerequisiteFailed,
+    /// The suite of the test comes before the suite the run started at.
+    BeforeStart,
 }
 
+impl SkipReason {
+    /// Returns the name of this reason, as it is serialized.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkipReason::Guard => "guard",
+            SkipReason::MandatoryPassed => "mandatory_passed",
+            SkipReason::Optional => "optional",
+            SkipReason::PreviousRun => "previous_run",
+            SkipReason::PrerequisiteFailed => "prerequisite_failed",
+            SkipReason::BeforeStart => "before_start",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum JsonCourseVersion {
     V1(JsonCourseV1),
 }
 
 pub trait Test {
-    fn run(&self) -> TestResult;
+    /// Runs the test.
+    ///
+    /// * `ctx`: settings affecting how the commands of the test are executed.
+    fn run(&self, ctx: &ExecContext) -> TestResult;
+
+    /// Checks whether a test should be skipped, returning the reason why if
+    /// so. Tests are never skipped by default.
+    ///
+    /// * `ctx`: settings affecting how the commands of the test are executed,
+    ///   only the binaries allowed to run apply to the check itself.
+    fn skip(&self, _ctx: &ExecContext) -> Option<String> {
+        None
+    }
 }
 
-pub fn load_course(path: &str) -> Result<JsonCourseVersion, ParsingError> {
-    log::debug!("Loading course '{path}'");
+/// Where a course is loaded from.
+#[derive(Clone, Debug)]
+pub enum CourseSource {
+    /// A single course file.
+    File(String),
+    /// A directory of course files, run as a single combined course.
+    Dir(String),
+    /// A single course split into a directory, with one file per suite.
+    Split(String),
+}
 
-    let file_contents = std::fs::read_to_string(path).map_err(|_| {
-        ParsingError::FileOpenError(format!("failed to open file at {path}"))
-    })?;
-    let json_raw = serde_json::from_str::<serde_json::Value>(&file_contents)
-        .map_err(|err| ParsingError::CourseFmtError(err.to_string()))?;
-    let version = json_raw.get("version").ok_or(()).map_err(|_| {
-        ParsingError::CourseFmtError(format!(
-            "missing field 'version' in {path}"
-        ))
-    })?;
+impl CourseSource {
+    /// Loads the course, see [load_course], [load_course_dir] and
+    /// [load_course_split].
+    pub fn load(&self) -> Result<JsonCourseVersion, ParsingError> {
+        self.load_with(&LoadOptions::default())
+    }
+
+    /// Loads the course with custom settings, such as a specific format
+    /// version regardless of the `version` it declares.
+    ///
+    /// * `opts`: settings affecting how the course is loaded.
+    pub fn load_with(
+        &self,
+        opts: &LoadOptions,
+    ) -> Result<JsonCourseVersion, ParsingError> {
+        let course = match self {
+            CourseSource::File(path) => load_course(path, opts),
+            CourseSource::Dir(dir) => load_course_dir(dir, opts),
+            CourseSource::Split(dir) => load_course_split(dir, opts),
+        }?;
 
-    match version {
-        Value::String(version) => match version.as_ref() {
-            V_1_0 => {
-                let json_course =
-                    serde_json::from_str::<JsonCourseV1>(&file_contents)
-                        .map_err(|err| {
-                            ParsingError::CourseFmtError(err.to_string())
-                        })?;
+        // Files of a directory are only checked once combined, as some of
+        // them may hold nothing but optional suites
+        let JsonCourseVersion::V1(v1) = &course;
+        if !v1.has_mandatory_tests() {
+            warn_once("course has no mandatory tests; it will always pass");
+        }
+
+        Ok(course)
+    }
+}
 
-                log::debug!("Course loaded successfully!");
+/// Loads every `*.json` course file in a directory and combines them into a
+/// single course.
+///
+/// Files are loaded in order of their file name, their suites being run one
+/// after the other. Files which fail to parse are reported and skipped rather
+/// than aborting the whole session.
+///
+/// * `dir`: directory containing the course files.
+/// * `opts`: settings affecting how the files are loaded.
+pub fn load_course_dir(
+    dir: &str,
+    opts: &LoadOptions,
+) -> Result<JsonCourseVersion, ParsingError> {
+    log::debug!("Loading courses in '{dir}'");
 
-                Ok(JsonCourseVersion::V1(json_course))
+    let entries =
+        std::fs::read_dir(dir).map_err(|err| ParsingError::io(dir, err))?;
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let courses = paths
+        .iter()
+        .filter_map(|path| match load_course(&path.to_string_lossy(), opts) {
+            Ok(JsonCourseVersion::V1(course)) => Some(course),
+            Err(err) => {
+                log::warn!("skipping {}: {err}", path.display());
+                None
             }
-            _ => Err(ParsingError::CourseFmtError(format!(
-                "invalid course version '{version}' in {path}"
-            ))),
-        },
-        _ => Err(ParsingError::CourseFmtError(format!(
+        })
+        .collect::<Vec<_>>();
+
+    let course = JsonCourseV1::merge(courses).ok_or_else(|| {
+        ParsingError::CourseFmtError(format!("no valid course files in {dir}"))
+    })?;
+
+    // Suites from different files may share a name once combined
+    check_duplicates(&course, opts)?;
+
+    Ok(JsonCourseVersion::V1(course))
+}
+
+/// Loads a course split into a directory, made of a [COURSE_HEADER] file
+/// with the metadata of the course and of `suite-*.json` files each holding a
+/// single suite.
+///
+/// Suite files are appended to the suites of the header, if it has any, in
+/// order of their file name. Unlike [load_course_dir], a file which fails to
+/// parse fails the whole course, as its suites are all part of it.
+///
+/// * `dir`: directory containing the course files.
+/// * `opts`: settings affecting how the files are loaded.
+pub fn load_course_split(
+    dir: &str,
+    opts: &LoadOptions,
+) -> Result<JsonCourseVersion, ParsingError> {
+    log::debug!("Loading split course in '{dir}'");
+
+    let header_path = Path::new(dir).join(COURSE_HEADER);
+    let header_path = header_path.to_string_lossy();
+    let (_, mut header) = read_json(&header_path)?;
+
+    let version = course_version(&header_path, &header, opts)?;
+    if version != V_1_0 {
+        return Err(ParsingError::UnsupportedVersion { found: version });
+    }
+
+    // The header holds metadata only, suites usually live in their own files
+    if let Some(header) = header.as_object_mut() {
+        header.entry("suites").or_insert(json!([]));
+    }
+    if let Some(err) = opts.strict.then(|| v1::unknown_field(&header)).flatten()
+    {
+        return Err(err);
+    }
+    let mut course = serde_json::from_value::<JsonCourseV1>(header.clone())
+        .map_err(|err| {
+            v1::missing_field(&header).unwrap_or_else(|| {
+                ParsingError::CourseFmtError(format!("{header_path}: {err}"))
+            })
+        })?;
+
+    let entries =
+        std::fs::read_dir(dir).map_err(|err| ParsingError::io(dir, err))?;
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name().and_then(|name| name.to_str()).is_some_and(
+                |name| name.starts_with("suite-") && name.ends_with(".json"),
+            )
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        let path = path.to_string_lossy();
+        let (contents, suite) = read_json(&path)?;
+
+        // Suites are checked as part of a course so that errors name them
+        let wrapped = json!({ "suites": [suite] });
+        if let Some(err) =
+            opts.strict.then(|| v1::unknown_field(&wrapped)).flatten()
+        {
+            return Err(err);
+        }
+        let suite = serde_json::from_str::<JsonTestSuiteV1>(&contents)
+            .map_err(|err| {
+                match err.classify() == Category::Data {
+                    true => v1::missing_field(&wrapped),
+                    false => None,
+                }
+                .unwrap_or(ParsingError::invalid_json(&path, err))
+            })?;
+
+        course.suites.push(suite);
+    }
+
+    check_duplicates(&course, opts)?;
+    check_env(&course, opts)?;
+    course
+        .read_expected_files(Path::new(dir))
+        .map_err(ParsingError::CourseFmtError)?;
+
+    log::debug!("Course loaded successfully!");
+
+    Ok(JsonCourseVersion::V1(course))
+}
+
+/// Rejects courses using environment variables which are not set when loading
+/// strictly. Otherwise, they are warned about as the tests using them run and
+/// expand to nothing.
+///
+/// * `course`: course to check.
+/// * `opts`: settings affecting how the course is loaded.
+fn check_env(
+    course: &JsonCourseV1,
+    opts: &LoadOptions,
+) -> Result<(), ParsingError> {
+    match opts.strict.then(|| course.undefined_var()).flatten() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Warns about courses in which suites or tests share a name, see
+/// [JsonCourseV1::duplicate_name], so that existing courses keep running. Such
+/// courses are rejected instead when loading strictly.
+///
+/// * `course`: course to check.
+/// * `opts`: settings affecting how the course is loaded.
+fn check_duplicates(
+    course: &JsonCourseV1,
+    opts: &LoadOptions,
+) -> Result<(), ParsingError> {
+    match (course.duplicate_name(), opts.strict) {
+        (Some(err), true) => Err(err),
+        (Some(err), false) => {
+            warn_once(&err.to_string());
+            Ok(())
+        }
+        (None, _) => Ok(()),
+    }
+}
+
+/// Logs a warning about a course unless the exact same warning was already
+/// logged, as courses are loaded again before each run with `--repeat`.
+///
+/// * `warning`: warning to log.
+fn warn_once(warning: &str) {
+    if WARNED.lock().unwrap().insert(warning.to_string()) {
+        log::warn!("{warning}");
+    }
+}
+
+/// Reads a JSON file, returning both its contents and the JSON they hold.
+///
+/// * `path`: path to the JSON file.
+fn read_json(path: &str) -> Result<(String, Value), ParsingError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| ParsingError::io(path, err))?;
+    // Some editors on Windows save JSON with a byte order mark, which serde
+    // rejects as an unexpected character
+    let contents = contents.trim_start_matches('\u{feff}').to_string();
+    let json = serde_json::from_str::<Value>(&contents)
+        .map_err(|err| ParsingError::invalid_json(path, err))?;
+
+    Ok((contents, json))
+}
+
+/// Determines which format version a course should be parsed as.
+///
+/// * `path`: path to the course file.
+/// * `json_raw`: contents of the course file.
+/// * `opts`: settings affecting how the file is loaded.
+fn course_version(
+    path: &str,
+    json_raw: &Value,
+    opts: &LoadOptions,
+) -> Result<String, ParsingError> {
+    match (opts.force_version, json_raw.get("version")) {
+        (Some(version), _) => {
+            log::warn!("Loading {path} as version {version}");
+            Ok(version.to_string())
+        }
+        (None, Some(Value::String(version))) => Ok(version.clone()),
+        (None, Some(_)) => Err(ParsingError::CourseFmtError(format!(
             "'version' must be a string in {path}"
         ))),
+        (None, None) => Err(ParsingError::MissingField {
+            suite: None,
+            test: None,
+            field: "version".to_string(),
+        }),
+    }
+}
+
+/// Loads a course file, parsing it according to its format version.
+///
+/// * `path`: path to the course file.
+/// * `opts`: settings affecting how the file is loaded.
+pub fn load_course(
+    path: &str,
+    opts: &LoadOptions,
+) -> Result<JsonCourseVersion, ParsingError> {
+    log::debug!("Loading course '{path}'");
+
+    let (file_contents, json_raw) = read_json(path)?;
+    let version = course_version(path, &json_raw, opts)?;
+
+    match version.as_str() {
+        V_1_0 => {
+            if let Some(err) =
+                opts.strict.then(|| v1::unknown_field(&json_raw)).flatten()
+            {
+                return Err(err);
+            }
+
+            let mut json_course = serde_json::from_str::<JsonCourseV1>(
+                &file_contents,
+            )
+            .map_err(|err| {
+                match err.classify() == Category::Data {
+                    true => v1::missing_field(&json_raw),
+                    false => None,
+                }
+                .unwrap_or(ParsingError::invalid_json(path, err))
+            })?;
+
+            check_duplicates(&json_course, opts)?;
+            check_env(&json_course, opts)?;
+            let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+            json_course
+                .read_expected_files(dir)
+                .map_err(ParsingError::CourseFmtError)?;
+
+            log::debug!("Course loaded successfully!");
+
+            Ok(JsonCourseVersion::V1(json_course))
+        }
+        _ => Err(ParsingError::UnsupportedVersion { found: version }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_course_skips_byte_order_mark() {
+        let course = serde_json::to_string(&JsonCourseV1::template()).unwrap();
+        let path = std::env::temp_dir()
+            .join(format!("dotcodeschool-bom-{}.json", std::process::id()));
+        std::fs::write(&path, format!("\u{feff}{course}")).unwrap();
+
+        let loaded =
+            load_course(&path.to_string_lossy(), &LoadOptions::default());
+        std::fs::remove_file(&path).unwrap();
+
+        let JsonCourseVersion::V1(loaded) = loaded.unwrap();
+        assert_eq!(loaded.name, "My Course");
+        assert_eq!(loaded.suites.len(), 1);
+    }
+
+    #[test]
+    fn load_course_dir_rejects_duplicates_across_files() {
+        let course = serde_json::to_string(&JsonCourseV1::template()).unwrap();
+        let dir = std::env::temp_dir()
+            .join(format!("dotcodeschool-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.json"), &course).unwrap();
+        std::fs::write(dir.join("b.json"), &course).unwrap();
+
+        let opts = LoadOptions { strict: true, ..Default::default() };
+        let loaded = load_course_dir(&dir.to_string_lossy(), &opts);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.unwrap_err().to_string().contains("duplicate name"));
     }
 }
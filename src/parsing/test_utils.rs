@@ -0,0 +1,40 @@
+//! Helpers for building courses in tests, shared by the tests of the parsing
+//! and runner modules.
+
+use serde_json::{json, Value};
+
+use super::v1::JsonCourseV1;
+
+/// Builds a course out of `suites`, given in the JSON course format.
+pub(crate) fn course(suites: Value) -> JsonCourseV1 {
+    serde_json::from_value(json!({
+        "version": "1.0",
+        "course": "Test Course",
+        "instructor": "Tester",
+        "course_id": 0,
+        "suites": suites,
+    }))
+    .unwrap()
+}
+
+/// Builds a suite named `name` in the JSON course format.
+pub(crate) fn suite(name: &str, tests: Value) -> Value {
+    json!({ "name": name, "optional": false, "tests": tests })
+}
+
+/// Builds a test named `name` in the JSON course format.
+pub(crate) fn test(name: &str, cmd: &str) -> Value {
+    json!({
+        "name": name,
+        "optional": false,
+        "cmd": cmd,
+        "message_on_fail": "failed",
+        "message_on_success": "passed",
+    })
+}
+
+/// Makes a suite or test built by [suite] or [test] optional.
+pub(crate) fn optional(mut value: Value) -> Value {
+    value["optional"] = json!(true);
+    value
+}
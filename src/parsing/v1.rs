@@ -1,56 +1,1850 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::Duration,
+};
+
 use serde::{Deserialize, Serialize};
 
-use super::{Test, TestResult};
+use serde_json::{json, Value};
+
+use super::{
+    exec::{execute, expand_vars, resolve, spawn, ExecContext},
+    ParsingError, Test, TestResult,
+};
+
+/// Replaces values masked by the `redact` field of tests.
+const REDACTED: &str = "[REDACTED]";
+
+lazy_static! {
+    static ref MISSING_FIELD: Regex =
+        Regex::new(r"^missing field `(?<field>\w+)`").unwrap();
+    static ref PERCENTAGE: Regex = Regex::new(r"(\d+(?:\.\d+)?)\s*%").unwrap();
+}
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct JsonTestV1 {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub optional: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub cmd: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cmds: Vec<JsonCommandV1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
     pub message_on_fail: String,
     pub message_on_success: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_coverage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_if: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_if_output_contains: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_tolerance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub expand_env: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_stdout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_stdout_file: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub binary_stdout: bool,
+    /// Contents of `expected_stdout_file` when `binary_stdout` is set, read
+    /// when the course is loaded.
+    #[serde(skip)]
+    pub expected_bytes: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<JsonAssertionV1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_in: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub background: bool,
+}
+
+/// A named check made against the output of the command of a test, so that a
+/// single run of an expensive command can be checked in several ways. Every
+/// condition which is set must hold for the assertion to pass.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct JsonAssertionV1 {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout_matches: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stderr_empty: bool,
+}
+
+impl JsonAssertionV1 {
+    /// Checks the assertion against the output of a command, returning a
+    /// description of the first condition which does not hold, if any.
+    ///
+    /// * `status`: how the command exited.
+    /// * `stdout`: standard output of the command.
+    /// * `stderr`: error output of the command.
+    fn check(
+        &self,
+        status: &ExitStatus,
+        stdout: &str,
+        stderr: &str,
+    ) -> Option<String> {
+        if let Some(code) = self.exit_code.filter(|c| status.code() != Some(*c))
+        {
+            return Some(match status.code() {
+                Some(found) => format!("exited with {found}, expected {code}"),
+                None => format!("was terminated, expected exit code {code}"),
+            });
+        }
+
+        if let Some(pattern) = &self.stdout_matches {
+            match Regex::new(pattern) {
+                Ok(regex) if regex.is_match(stdout) => {}
+                Ok(_) => {
+                    return Some(format!("stdout does not match {pattern}"))
+                }
+                Err(err) => return Some(format!("invalid pattern: {err}")),
+            }
+        }
+
+        (self.stderr_empty && !stderr.trim().is_empty())
+            .then(|| "stderr is not empty".to_string())
+    }
+}
+
+/// A problem found in a course by [JsonCourseV1::validate].
+///
+/// * `suite`: name of the suite the problem affects, if any.
+/// * `test`: name of the test the problem affects, if any.
+/// * `message`: description of the problem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub suite: Option<String>,
+    pub test: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.suite, &self.test) {
+            (Some(suite), Some(test)) => {
+                write!(f, "{suite} / {test}: {}", self.message)
+            }
+            (Some(suite), None) => write!(f, "{suite}: {}", self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// A step of a test with several `cmds`, either a plain command or a command
+/// with its own message displayed if it is the step which fails.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum JsonCommandV1 {
+    Plain(String),
+    WithMessage { cmd: String, message_on_fail: String },
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct JsonTestSuiteV1 {
     pub name: String,
     pub optional: bool,
     pub tests: Vec<JsonTestV1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parallel: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requires_suite: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct JsonCourseV1 {
     pub version: String,
     #[serde(rename = "course")]
     pub name: String,
     pub instructor: String,
     pub course_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
     pub suites: Vec<JsonTestSuiteV1>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub score_messages: BTreeMap<u32, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_rust_version: Option<String>,
+}
+
+impl JsonCourseV1 {
+    /// Returns a minimal course with a single suite and a single test, used
+    /// as a starting point for course authors.
+    pub fn template() -> Self {
+        Self {
+            version: super::V_1_0.to_string(),
+            name: "My Course".to_string(),
+            instructor: "Your Name".to_string(),
+            course_id: 0,
+            branch: None,
+            suites: vec![JsonTestSuiteV1 {
+                name: "First Suite".to_string(),
+                optional: false,
+                tests: vec![JsonTestV1 {
+                    name: "First Test".to_string(),
+                    optional: false,
+                    cmd: "cargo test first_test".to_string(),
+                    message_on_fail: "This test failed, back to the drawing \
+                                      board."
+                        .to_string(),
+                    message_on_success: "This test passed, congrats!"
+                        .to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            score_messages: BTreeMap::new(),
+            min_rust_version: None,
+        }
+    }
+
+    /// Returns a JSON Schema describing V1 course files, for use by editors to
+    /// validate and autocomplete courses as they are authored.
+    ///
+    /// This is written by hand and must be kept in sync with the fields of
+    /// [JsonCourseV1], [JsonTestSuiteV1] and [JsonTestV1].
+    pub fn schema() -> Value {
+        let test = json!({
+            "type": "object",
+            "required": [
+                "name",
+                "optional",
+                "message_on_fail",
+                "message_on_success"
+            ],
+            "properties": {
+                "name": { "type": "string" },
+                "description": {
+                    "type": "string",
+                    "description":
+                        "One-line explanation displayed when the test runs."
+                },
+                "optional": { "type": "boolean" },
+                "cmd": {
+                    "type": "string",
+                    "description":
+                        "Command to run, passing if it exits successfully."
+                },
+                "cmds": {
+                    "type": "array",
+                    "items": {
+                        "oneOf": [
+                            { "type": "string" },
+                            {
+                                "type": "object",
+                                "required": ["cmd", "message_on_fail"],
+                                "properties": {
+                                    "cmd": { "type": "string" },
+                                    "message_on_fail": { "type": "string" }
+                                }
+                            }
+                        ]
+                    },
+                    "description":
+                        "Commands to run in order, takes precedence over cmd."
+                },
+                "interpreter": {
+                    "type": "string",
+                    "description": "Program running each command, such as \
+                                    python3."
+                },
+                "message_on_fail": { "type": "string" },
+                "message_on_success": { "type": "string" },
+                "coverage": {
+                    "type": "string",
+                    "description": "Command reporting a coverage percentage."
+                },
+                "min_coverage": {
+                    "type": "number",
+                    "minimum": 0,
+                    "maximum": 100
+                },
+                "order": { "type": "integer" },
+                "skip_if": {
+                    "type": "string",
+                    "description": "Command skipping the test if it succeeds."
+                },
+                "timeout": {
+                    "type": "number",
+                    "exclusiveMinimum": 0,
+                    "description": "Seconds each command can run for."
+                },
+                "retries": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Number of times to retry a failed test."
+                },
+                "retry_if_output_contains": {
+                    "type": "string",
+                    "description":
+                        "Only retries failures whose output contains this."
+                },
+                "expected_duration_ms": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Milliseconds the test is expected to run \
+                                    for at most."
+                },
+                "duration_tolerance": {
+                    "type": "number",
+                    "minimum": 0,
+                    "description": "Percentage by which the test can exceed \
+                                    expected_duration_ms."
+                },
+                "working_dir": {
+                    "type": "string",
+                    "description": "Directory the commands are run in, \
+                                    relative to --root."
+                },
+                "expand_env": {
+                    "type": "boolean",
+                    "description": "Replaces $VAR and ${VAR} in the commands \
+                                    and working_dir with environment \
+                                    variables."
+                },
+                "expected_stdout": {
+                    "type": "string",
+                    "description": "Output the commands must print."
+                },
+                "expected_stdout_file": {
+                    "type": "string",
+                    "description": "File holding the output the commands must \
+                                    print, relative to the course file."
+                },
+                "binary_stdout": {
+                    "type": "boolean",
+                    "description": "Compares the output with \
+                                    expected_stdout_file byte by byte."
+                },
+                "redact": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Values masked in the output of the test, \
+                                    either literal, env:VARIABLE or \
+                                    regex:PATTERN."
+                },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Skills checked by the test, results being \
+                                    grouped by them with --group-by tag."
+                },
+                "added_in": {
+                    "type": "string",
+                    "description": "Version of the course the test was added \
+                                    in, highlighted with --highlight-new."
+                },
+                "background": {
+                    "type": "boolean",
+                    "description": "Starts the commands without waiting for \
+                                    them, stopping them once the suite ends."
+                },
+                "assertions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "exit_code": { "type": "integer" },
+                            "stdout_matches": { "type": "string" },
+                            "stderr_empty": { "type": "boolean" }
+                        }
+                    },
+                    "description": "Checks made against a single run of cmd, \
+                                    each reported separately."
+                }
+            }
+        });
+
+        let suite = json!({
+            "type": "object",
+            "required": ["name", "optional", "tests"],
+            "properties": {
+                "name": { "type": "string" },
+                "optional": { "type": "boolean" },
+                "tests": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/test" }
+                },
+                "order": { "type": "integer" },
+                "max_parallel": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Maximum number of tests of the suite run \
+                                    at the same time with --jobs."
+                },
+                "requires_suite": {
+                    "type": "string",
+                    "description": "Suite whose failure skips this suite."
+                }
+            }
+        });
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "DotCodeSchool course",
+            "type": "object",
+            "required": [
+                "version",
+                "course",
+                "instructor",
+                "course_id",
+                "suites"
+            ],
+            "properties": {
+                "version": { "enum": [super::V_1_0] },
+                "course": { "type": "string" },
+                "instructor": { "type": "string" },
+                "course_id": { "type": "integer", "minimum": 0 },
+                "branch": {
+                    "type": "string",
+                    "description":
+                        "Git branch students are expected to work on."
+                },
+                "suites": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/suite" }
+                },
+                "score_messages": {
+                    "type": "object",
+                    "propertyNames": { "pattern": "^[0-9]+$" },
+                    "additionalProperties": { "type": "string" },
+                    "description": "Messages displayed once every mandatory \
+                                    test has passed, keyed by the minimum \
+                                    score percentage they apply to."
+                },
+                "min_rust_version": {
+                    "type": "string",
+                    "pattern": "^[0-9]+(\\.[0-9]+){0,2}$",
+                    "description": "Oldest Rust toolchain the course can be \
+                                    run with, such as 1.80."
+                }
+            },
+            "$defs": { "suite": suite, "test": test }
+        })
+    }
+
+    /// Combines several courses into a single course, in order.
+    ///
+    /// The resulting course has the suites of every course, one after the
+    /// other, and the names and instructors of every course. Returns [None] if
+    /// no courses are given.
+    ///
+    /// * `courses`: courses to combine.
+    pub fn merge(courses: Vec<Self>) -> Option<Self> {
+        let mut courses = courses.into_iter();
+        let mut merged = courses.next()?;
+
+        for course in courses {
+            merged.name = format!("{} + {}", merged.name, course.name);
+            if !merged.instructor.split(", ").any(|i| i == course.instructor) {
+                merged.instructor =
+                    format!("{}, {}", merged.instructor, course.instructor);
+            }
+            merged.suites.extend(course.suites);
+            for (score, message) in course.score_messages {
+                merged.score_messages.entry(score).or_insert(message);
+            }
+            // The combined course needs the newest of the toolchains
+            merged.min_rust_version =
+                [merged.min_rust_version.take(), course.min_rust_version]
+                    .into_iter()
+                    .flatten()
+                    .max_by_key(|version| parse_version(version));
+        }
+
+        Some(merged)
+    }
+
+    /// Returns the binaries run by the tests of the course, including their
+    /// `coverage` and `skip_if` checks, which cannot be found, each listed once
+    /// in the order in which they are first used. Commands are never executed.
+    pub fn missing_binaries(&self) -> Vec<String> {
+        let mut missing = Vec::<String>::new();
+
+        for test in self.suites.iter().flat_map(|suite| &suite.tests) {
+            let commands = test.required_commands();
+
+            for cmd in commands.iter().filter(|cmd| !resolve(cmd)) {
+                let binary = cmd.split_whitespace().next().unwrap_or_default();
+                if !binary.is_empty() && !missing.iter().any(|b| b == binary) {
+                    missing.push(binary.to_string());
+                }
+            }
+        }
+
+        missing
+    }
+
+    /// Returns the position of every suite sharing its name with an earlier
+    /// suite of the course, as `(index_suite, None)`, and of every test sharing
+    /// its name with an earlier test of its suite, as
+    /// `(index_suite, Some(index_test))`.
+    fn duplicates(&self) -> Vec<(usize, Option<usize>)> {
+        let mut duplicates = Vec::new();
+
+        for (index_suite, suite) in self.suites.iter().enumerate() {
+            if self.suites[..index_suite].iter().any(|s| s.name == suite.name) {
+                duplicates.push((index_suite, None));
+            }
+
+            for (index_test, test) in suite.tests.iter().enumerate() {
+                if suite.tests[..index_test].iter().any(|t| t.name == test.name)
+                {
+                    duplicates.push((index_suite, Some(index_test)));
+                }
+            }
+        }
+
+        duplicates
+    }
+
+    /// Looks for suites sharing a name within the course, or tests sharing a
+    /// name within a suite. Returns [None] if every name is unique.
+    ///
+    /// Suites and tests are told apart by name when selecting them and in
+    /// results, so duplicates would make both ambiguous.
+    pub fn duplicate_name(&self) -> Option<ParsingError> {
+        let (index_suite, index_test) = *self.duplicates().first()?;
+        let suite = &self.suites[index_suite];
+        let (name, location) = match index_test {
+            Some(index_test) => {
+                (&suite.tests[index_test].name, Some(suite.name.clone()))
+            }
+            None => (&suite.name, None),
+        };
+
+        Some(ParsingError::CourseFmtError(format!(
+            "duplicate name '{name}'{}",
+            super::location(&location, &None)
+        )))
+    }
+
+    /// Looks for environment variables used by tests setting `expand_env`
+    /// which are not set. Returns [None] if every variable is set.
+    pub fn undefined_var(&self) -> Option<ParsingError> {
+        self.suites.iter().find_map(|suite| {
+            suite.tests.iter().find_map(|test| {
+                let var = test.undefined_vars().into_iter().next()?;
+                Some(ParsingError::CourseFmtError(format!(
+                    "environment variable '{var}' is not set{}",
+                    super::location(
+                        &Some(suite.name.clone()),
+                        &Some(test.name.clone())
+                    )
+                )))
+            })
+        })
+    }
+
+    /// Looks for mistakes in the course which would only be noticed once the
+    /// affected test runs, such as tests without a command or commands whose
+    /// binary cannot be found. Commands are never executed.
+    ///
+    /// Returns every problem found rather than stopping at the first, along
+    /// with the suite and test it affects.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut problems = Vec::new();
+        let course = |message: String| ValidationError {
+            suite: None,
+            test: None,
+            message,
+        };
+
+        if self.suites.is_empty() {
+            problems.push(course("course has no suites".to_string()));
+        }
+
+        if let Some(version) = &self.min_rust_version {
+            if parse_version(version).is_none() {
+                problems.push(course(format!(
+                    "`min_rust_version` '{version}' is not a version such as \
+                     1.80"
+                )));
+            }
+        }
+
+        if self.score_messages.keys().any(|score| *score > 100) {
+            problems.push(course(
+                "`score_messages` scores must be between 0 and 100".to_string(),
+            ));
+        }
+
+        let duplicates = self.duplicates();
+        for (index_suite, suite) in self.suites.iter().enumerate() {
+            let mut problem = |message: String| {
+                problems.push(ValidationError {
+                    suite: Some(suite.name.clone()),
+                    test: None,
+                    message,
+                })
+            };
+
+            if suite.tests.is_empty() {
+                problem("suite has no tests".to_string());
+            }
+            if duplicates.contains(&(index_suite, None)) {
+                problem("another suite of the course has this name".into());
+            }
+            if suite.max_parallel == Some(0) {
+                problem("`max_parallel` must be at least 1".to_string());
+            }
+            if let Some(required) = &suite.requires_suite {
+                if !self.suites[..index_suite]
+                    .iter()
+                    .any(|earlier| earlier.name == *required)
+                {
+                    problem(format!(
+                        "`requires_suite` '{required}' is not a suite run \
+                         before it"
+                    ));
+                }
+            }
+
+            for (index_test, test) in suite.tests.iter().enumerate() {
+                let mut problem = |message: String| {
+                    problems.push(ValidationError {
+                        suite: Some(suite.name.clone()),
+                        test: Some(test.name.clone()),
+                        message,
+                    })
+                };
+
+                if let Some(version) = &test.added_in {
+                    if parse_version(version).is_none() {
+                        problem(format!(
+                            "`added_in` '{version}' is not a version such as \
+                             1.2"
+                        ));
+                    }
+                }
+
+                // Tests are told apart by name in results and --test
+                if duplicates.contains(&(index_suite, Some(index_test))) {
+                    problem("another test of the suite has this name".into());
+                }
+
+                let commands = test.commands();
+                if commands.iter().all(|cmd| cmd.trim().is_empty()) {
+                    problem("no `cmd` or `cmds` to run".to_string());
+                }
+
+                for cmd in test.required_commands() {
+                    if !resolve(&cmd) {
+                        problem(format!(
+                            "could not find the binary of '{cmd}'"
+                        ));
+                    }
+                }
+
+                for var in test.undefined_vars() {
+                    problem(format!(
+                        "environment variable '{var}' used with `expand_env` \
+                         is not set"
+                    ));
+                }
+
+                if test.min_coverage.is_some() && test.coverage.is_none() {
+                    problem(
+                        "`min_coverage` is set without `coverage`".to_string(),
+                    );
+                }
+                if test
+                    .min_coverage
+                    .is_some_and(|min| !(0.0..=100.0).contains(&min))
+                {
+                    problem(
+                        "`min_coverage` must be between 0 and 100".to_string(),
+                    );
+                }
+                let checks_output = test.expected_stdout.is_some()
+                    || test.expected_stdout_file.is_some()
+                    || test.coverage.is_some()
+                    || !test.assertions.is_empty();
+                if test.background && checks_output {
+                    problem(
+                        "`background` tests are not waited for, so their \
+                         output cannot be checked"
+                            .to_string(),
+                    );
+                }
+                if !test.assertions.is_empty() && !test.cmds.is_empty() {
+                    problem(
+                        "`assertions` only apply to `cmd`, not `cmds`"
+                            .to_string(),
+                    );
+                }
+                for assertion in &test.assertions {
+                    if assertion.exit_code.is_none()
+                        && assertion.stdout_matches.is_none()
+                        && !assertion.stderr_empty
+                    {
+                        problem(format!(
+                            "assertion '{}' does not check anything",
+                            assertion.name
+                        ));
+                    }
+                    if let Some(Err(err)) =
+                        assertion.stdout_matches.as_deref().map(Regex::new)
+                    {
+                        problem(format!(
+                            "assertion '{}' has an invalid pattern: {err}",
+                            assertion.name
+                        ));
+                    }
+                }
+                if test.retry_if_output_contains.is_some() && test.retries == 0
+                {
+                    problem(
+                        "`retry_if_output_contains` is set without `retries`"
+                            .to_string(),
+                    );
+                }
+                if test.timeout.is_some_and(|timeout| timeout <= 0.0) {
+                    problem("`timeout` must be positive".to_string());
+                }
+                if test.duration_tolerance.is_some()
+                    && test.expected_duration_ms.is_none()
+                {
+                    problem(
+                        "`duration_tolerance` is set without \
+                         `expected_duration_ms`"
+                            .to_string(),
+                    );
+                }
+                if test.binary_stdout && test.expected_stdout_file.is_none() {
+                    problem(
+                        "`binary_stdout` is set without `expected_stdout_file`"
+                            .to_string(),
+                    );
+                }
+                for pattern in test
+                    .redact
+                    .iter()
+                    .filter_map(|value| value.strip_prefix("regex:"))
+                {
+                    if let Err(err) = Regex::new(pattern) {
+                        problem(format!(
+                            "invalid pattern in `redact` '{pattern}': {err}"
+                        ));
+                    }
+                }
+                if test.duration_tolerance.is_some_and(|t| t < 0.0) {
+                    problem(
+                        "`duration_tolerance` must not be negative".to_string(),
+                    );
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Reads the `expected_stdout_file` of every test into its
+    /// `expected_stdout`, or its `expected_bytes` for binary outputs, so that
+    /// tests do not depend on where the course file
+    /// was loaded from once it has been loaded.
+    ///
+    /// * `dir`: directory containing the course file, which expected output
+    ///   files are relative to.
+    pub fn read_expected_files(&mut self, dir: &Path) -> Result<(), String> {
+        for suite in self.suites.iter_mut() {
+            for test in suite.tests.iter_mut() {
+                let Some(file) = &test.expected_stdout_file else {
+                    continue;
+                };
+
+                if test.expected_stdout.is_some() {
+                    return Err(format!(
+                        "{} / {}: `expected_stdout` and \
+                         `expected_stdout_file` are both set",
+                        suite.name, test.name
+                    ));
+                }
+
+                let path = dir.join(file);
+                let read_error = |err: std::io::Error| {
+                    format!(
+                        "{} / {}: could not read {}: {err}",
+                        suite.name,
+                        test.name,
+                        path.display()
+                    )
+                };
+
+                // Binary outputs are not valid text, so they are kept as is
+                match test.binary_stdout {
+                    true => {
+                        let expected =
+                            std::fs::read(&path).map_err(read_error)?;
+                        test.expected_bytes = Some(expected);
+                    }
+                    false => {
+                        let expected = std::fs::read_to_string(&path)
+                            .map_err(read_error)?;
+                        test.expected_stdout = Some(expected);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy of the course holding only the tests with a given name,
+    /// or [None] if there are none.
+    ///
+    /// Selected tests are made mandatory, so that a failure of any of them
+    /// fails the run.
+    ///
+    /// * `name`: name of the tests to keep.
+    pub fn only_test(&self, name: &str) -> Option<Self> {
+        let mut course = self.clone();
+
+        for suite in course.suites.iter_mut() {
+            suite.optional = false;
+            suite.tests.retain(|test| test.name == name);
+            for test in suite.tests.iter_mut() {
+                test.optional = false;
+            }
+        }
+        course.suites.retain(|suite| !suite.tests.is_empty());
+
+        (!course.suites.is_empty()).then_some(course)
+    }
+
+    /// Returns the index of a suite, given either its name or its position in
+    /// the course starting from 1.
+    ///
+    /// * `suite`: name or position of the suite.
+    pub fn suite_index(&self, suite: &str) -> Option<usize> {
+        let by_name = self.suites.iter().position(|s| s.name == suite);
+        let by_position = suite
+            .parse::<usize>()
+            .ok()
+            .filter(|position| (1..=self.suites.len()).contains(position))
+            .map(|position| position - 1);
+
+        by_name.or(by_position)
+    }
+
+    /// Checks whether the course has at least one mandatory test, that is a
+    /// test which is not optional in a suite which is not optional. Courses
+    /// without any always pass, whatever the results of their tests.
+    pub fn has_mandatory_tests(&self) -> bool {
+        self.suites
+            .iter()
+            .filter(|suite| !suite.optional)
+            .any(|suite| suite.tests.iter().any(|test| !test.optional))
+    }
+
+    /// Returns the message from `score_messages` for the highest score band
+    /// reached, if any.
+    ///
+    /// * `score`: score percentage of the run.
+    pub fn score_message(&self, score: f64) -> Option<&str> {
+        self.score_messages
+            .iter()
+            .rev()
+            .find(|(min, _)| score >= f64::from(**min))
+            .map(|(_, message)| message.as_str())
+    }
+
+    /// Whether any mandatory test comes after a given test, in running order.
+    ///
+    /// * `index_suite`: index of the suite of the test.
+    /// * `index_test`: index of the test in its suite.
+    pub fn has_mandatory_after(
+        &self,
+        index_suite: usize,
+        index_test: usize,
+    ) -> bool {
+        self.suites.iter().enumerate().skip(index_suite).any(
+            |(index, suite)| {
+                let start =
+                    if index == index_suite { index_test + 1 } else { 0 };
+                suite
+                    .tests
+                    .iter()
+                    .skip(start)
+                    .any(|test| suite.is_mandatory(test))
+            },
+        )
+    }
+
+    /// Returns the total number of tests across all suites.
+    pub fn test_count(&self) -> usize {
+        self.suites.iter().fold(0, |acc, suite| acc + suite.tests.len())
+    }
+
+    /// Total number of mandatory tests in a course, see
+    /// [JsonTestSuiteV1::is_mandatory].
+    pub fn mandatory_count(&self) -> usize {
+        self.suites
+            .iter()
+            .map(|suite| {
+                suite.tests.iter().filter(|t| suite.is_mandatory(t)).count()
+            })
+            .sum()
+    }
+
+    /// Sorts suites and the tests within them by their `order` field.
+    ///
+    /// Suites and tests without an `order` are treated as having an order of
+    /// 0. Sorting is stable, so elements with the same order keep the order in
+    /// which they were defined, meaning a course without any `order` field
+    /// runs strictly in array order.
+    pub fn sort(&mut self) {
+        self.suites.sort_by_key(|suite| suite.order.unwrap_or(0));
+        for suite in self.suites.iter_mut() {
+            suite.tests.sort_by_key(|test| test.order.unwrap_or(0));
+        }
+    }
+
+    /// Shuffles the tests within each suite in an order derived from `seed`,
+    /// so that the same seed always gives the same order. Suites keep their
+    /// order, as do tests with an explicit `order`, which stay in place.
+    ///
+    /// The seed is hashed with FNV-1a and drives a SplitMix64 generator, both
+    /// written out here so that orders do not change between Rust versions.
+    ///
+    /// * `seed`: string identifying the order, such as a student ID.
+    pub fn shuffle(&mut self, seed: &str) {
+        let mut state = seed.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        let mut next = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+
+        for suite in self.suites.iter_mut() {
+            let free = (0..suite.tests.len())
+                .filter(|index| suite.tests[*index].order.is_none())
+                .collect::<Vec<_>>();
+
+            // Fisher-Yates over the positions of the tests which can move
+            for i in (1..free.len()).rev() {
+                let j = (next() % (i as u64 + 1)) as usize;
+                suite.tests.swap(free[i], free[j]);
+            }
+        }
+    }
+}
+
+impl JsonTestSuiteV1 {
+    /// Whether a test of this suite must pass for the course to be validated.
+    ///
+    /// Every test of an optional suite is optional, whatever its own
+    /// `optional` field says. Optional tests only affect whether the run
+    /// stops at their failure, they count towards the score like any other.
+    ///
+    /// * `test`: test of this suite.
+    pub fn is_mandatory(&self, test: &JsonTestV1) -> bool {
+        !self.optional && !test.optional
+    }
 }
 
 impl Test for JsonTestV1 {
-    fn run(&self) -> TestResult {
-        log::debug!("Running test: '{}'", self.cmd);
+    fn run(&self, ctx: &ExecContext) -> TestResult {
+        let mut attempt = 0;
+
+        loop {
+            match self.attempt(ctx).map_output(|output| self.redacted(output)) {
+                TestResult::Fail(output)
+                | TestResult::StepFail { output, .. }
+                    if self.should_retry(attempt, &output) =>
+                {
+                    attempt += 1;
+                    log::info!(
+                        "Retrying test '{}' ({attempt}/{})",
+                        self.name,
+                        self.retries
+                    );
+                    if let Some(on_retry) = ctx.on_retry {
+                        on_retry();
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
 
-        let command: Vec<&str> = self.cmd.split_whitespace().collect();
+    fn skip(&self, ctx: &ExecContext) -> Option<String> {
+        let guard = self.skip_if.as_ref()?;
 
-        let output = std::process::Command::new(command[0])
-            .args(command[1..].into_iter())
-            .output();
-        let output = match output {
-            Ok(output) => output,
-            Err(_) => {
-                return TestResult::Fail("could not execute test".to_string())
+        log::debug!("Checking guard: '{guard}'");
+
+        // The guard is not part of the test, so neither its output nor the
+        // resources it uses are reported as the test's, and extra arguments
+        // are only meant for the commands of the test
+        let ctx = ExecContext {
+            on_line: None,
+            on_usage: None,
+            on_timeout: None,
+            on_output: None,
+            args: &[],
+            ..*ctx
+        };
+        match execute(guard, &ctx) {
+            Ok(output) if output.status.success() => {
+                Some(format!("'{guard}' succeeded"))
             }
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!("{err}, running test");
+                None
+            }
+        }
+    }
+}
+
+impl JsonTestV1 {
+    /// Runs the commands of the test once, followed by its coverage check.
+    ///
+    /// * `ctx`: settings affecting how the commands are executed.
+    fn attempt(&self, ctx: &ExecContext) -> TestResult {
+        let timeout = self.timeout.map(Duration::from_secs_f64);
+        // Joining an absolute working directory replaces the root entirely
+        let working_dir = self.working_dir.as_deref().map(|dir| {
+            let dir = self.expanded(dir, true);
+            ctx.root.map_or_else(
+                || PathBuf::from(dir.as_ref()),
+                |root| root.join(dir.as_ref()),
+            )
+        });
+        // Extra arguments are only meant for the commands of the test, not
+        // for its coverage check
+        let args = ctx.args;
+        let ctx = &ExecContext {
+            timeout,
+            working_dir: working_dir.as_deref(),
+            args: &[],
+            expand_env: self.expand_env,
+            ..*ctx
         };
+        let interpreter = self.interpreter.as_deref();
+        // Binary outputs are compared in full, and one byte past the expected
+        // output is enough to tell that there is too much of it
+        let max_output = match &self.expected_bytes {
+            Some(expected) => {
+                ctx.max_output.map(|max| max.max(expected.len() + 1))
+            }
+            None => ctx.max_output,
+        };
+        let cmd_ctx = &ExecContext { interpreter, args, max_output, ..*ctx };
+        if self.background {
+            return self.start_background(cmd_ctx);
+        }
+        if !self.assertions.is_empty() {
+            return self.check_assertions(cmd_ctx);
+        }
+
+        let commands = self.commands();
+        let mut stdout = String::new();
+        let mut bytes = Vec::new();
+
+        for (index, cmd) in commands.iter().enumerate() {
+            log::debug!("Running test: '{cmd}'");
+
+            // Identifies the failing step when running multiple commands
+            let step = match commands.len() {
+                1 => String::new(),
+                count => format!("step {}/{count} '{cmd}' ", index + 1),
+            };
+
+            let output = match execute(cmd, cmd_ctx) {
+                Ok(output) => output,
+                Err(err) => return TestResult::Fail(err.to_string()),
+            };
+
+            if !output.status.success() {
+                let stderr =
+                    String::from_utf8_lossy(&output.stderr).to_string();
+                let stderr = match step.is_empty() {
+                    true => stderr,
+                    false => format!("{step}failed\n{stderr}"),
+                };
+
+                // Steps can replace the message of the test when they fail
+                return match self
+                    .cmds
+                    .get(index)
+                    .and_then(JsonCommandV1::message_on_fail)
+                {
+                    Some(message) => TestResult::StepFail {
+                        output: stderr,
+                        message: message.to_string(),
+                    },
+                    None => TestResult::Fail(stderr),
+                };
+            }
+
+            stdout.push_str(&String::from_utf8_lossy(&output.stdout));
+            bytes.extend_from_slice(&output.stdout);
+        }
 
         log::debug!("Test executed successfully!");
 
-        match output.status.success() {
-            true => TestResult::Pass(String::from_utf8(output.stdout).unwrap()),
-            false => {
-                TestResult::Fail(String::from_utf8(output.stderr).unwrap())
+        if let Some(expected) = &self.expected_bytes {
+            return match first_difference(expected, &bytes) {
+                Some(offset) => TestResult::Fail(format!(
+                    "unexpected output: first difference at byte {offset} \
+                     (expected {} bytes, found {})",
+                    expected.len(),
+                    bytes.len()
+                )),
+                None => TestResult::Pass(format!(
+                    "{} bytes of output match",
+                    bytes.len()
+                )),
+            };
+        }
+
+        if let Some(expected) = &self.expected_stdout {
+            // Editors commonly add a final newline to expected output files
+            if stdout.trim_end() != expected.trim_end() {
+                return TestResult::Fail(format!(
+                    "unexpected output\n--- expected\n+++ found\n{}",
+                    diff(
+                        expected.trim_end(),
+                        stdout.trim_end(),
+                        ctx.diff_context
+                    )
+                ));
+            }
+        }
+
+        match &self.coverage {
+            Some(coverage) => self.check_coverage(coverage, stdout, ctx),
+            None => TestResult::Pass(stdout),
+        }
+    }
+
+    /// Checks whether the test was added in `version` of the course or later.
+    /// Tests which do not say when they were added never are.
+    ///
+    /// * `version`: oldest version whose tests are new.
+    pub fn is_new_since(&self, version: &str) -> bool {
+        let added_in = self.added_in.as_deref().and_then(parse_version);
+        added_in
+            .is_some_and(|added_in| Some(added_in) >= parse_version(version))
+    }
+
+    /// Starts the commands of the test in the background, handing them over to
+    /// [ExecContext::on_background] to be stopped later. The test passes if
+    /// every command could be started.
+    ///
+    /// * `ctx`: settings affecting how the commands are executed.
+    fn start_background(&self, ctx: &ExecContext) -> TestResult {
+        for cmd in self.commands() {
+            log::debug!("Starting in the background: '{cmd}'");
+
+            match (spawn(cmd, ctx), ctx.on_background) {
+                (Ok(child), Some(on_background)) => on_background(child),
+                (Ok(child), None) => log::warn!(
+                    "Nothing will stop '{cmd}' (process {}), leaving it \
+                     running",
+                    child.id()
+                ),
+                (Err(err), _) => return TestResult::Fail(err.to_string()),
+            }
+        }
+
+        TestResult::Pass(String::new())
+    }
+
+    /// Runs the command of the test once and checks each of its `assertions`
+    /// against its output. The test passes if every assertion does, its
+    /// output listing the result of each of them.
+    ///
+    /// * `ctx`: settings affecting how the command is executed.
+    fn check_assertions(&self, ctx: &ExecContext) -> TestResult {
+        log::debug!("Running test: '{}'", self.cmd);
+
+        let output = match execute(&self.cmd, ctx) {
+            Ok(output) => output,
+            Err(err) => return TestResult::Fail(err.to_string()),
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        let mut failed = false;
+        let report = self
+            .assertions
+            .iter()
+            .map(|assertion| {
+                match assertion.check(&output.status, &stdout, &stderr) {
+                    Some(problem) => {
+                        failed = true;
+                        format!("- {}: failed, {problem}", assertion.name)
+                    }
+                    None => format!("- {}: passed", assertion.name),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        match failed {
+            true => TestResult::Fail(report),
+            false => TestResult::Pass(report),
+        }
+    }
+
+    /// Masks the values listed in `redact` in the output of the test, so that
+    /// secrets used by tests do not end up in logs or reports.
+    ///
+    /// Values are taken literally unless prefixed with `env:`, standing for the
+    /// value of an environment variable, or `regex:`, standing for a pattern.
+    ///
+    /// * `output`: output to redact.
+    pub fn redacted(&self, output: String) -> String {
+        self.redact.iter().fold(output, |output, value| {
+            let pattern = match (
+                value.strip_prefix("env:"),
+                value.strip_prefix("regex:"),
+            ) {
+                (Some(var), _) => match std::env::var(var) {
+                    Ok(secret) if !secret.is_empty() => regex::escape(&secret),
+                    _ => return output,
+                },
+                (_, Some(pattern)) => pattern.to_string(),
+                _ => regex::escape(value),
+            };
+
+            match Regex::new(&pattern) {
+                Ok(regex) => regex.replace_all(&output, REDACTED).to_string(),
+                Err(err) => {
+                    log::warn!("Invalid pattern in `redact` '{value}': {err}");
+                    output
+                }
+            }
+        })
+    }
+
+    /// Decides whether a failed attempt at running the test should be retried.
+    ///
+    /// Tests are retried up to `retries` times. If `retry_if_output_contains`
+    /// is set, only failures whose output contains it are retried so that
+    /// genuine regressions are not masked by retries.
+    ///
+    /// * `attempt`: number of retries so far.
+    /// * `output`: output of the failed attempt.
+    fn should_retry(&self, attempt: u32, output: &str) -> bool {
+        attempt < self.retries
+            && self
+                .retry_if_output_contains
+                .as_ref()
+                .is_none_or(|pattern| output.contains(pattern.as_str()))
+    }
+
+    /// Checks how long the test took against `expected_duration_ms`, returning
+    /// a description of the problem if it took longer than expected.
+    ///
+    /// * `duration`: time taken to run the test.
+    pub fn check_duration(&self, duration: Duration) -> Option<String> {
+        let expected = self.expected_duration_ms?;
+        let tolerance = self.duration_tolerance.unwrap_or(0.0);
+        let limit = expected as f64 * (1.0 + tolerance / 100.0);
+        let took = duration.as_millis();
+
+        (took as f64 > limit).then(|| {
+            format!("test took {took}ms, expected <{}ms", limit.round())
+        })
+    }
+
+    /// Returns the commands to run for this test, in order.
+    ///
+    /// `cmds` takes precedence over `cmd` when it is not empty, otherwise `cmd`
+    /// is treated as a single command.
+    pub fn commands(&self) -> Vec<&str> {
+        match self.cmds.is_empty() {
+            true => vec![self.cmd.as_str()],
+            false => self.cmds.iter().map(JsonCommandV1::cmd).collect(),
+        }
+    }
+
+    /// Returns the commands of the test whose binary must be found for it to
+    /// run, as they will be run: its commands followed by its `coverage` and
+    /// `skip_if` checks. Empty commands are left out.
+    fn required_commands(&self) -> Vec<Cow<'_, str>> {
+        // Commands are run by the interpreter if there is one, so only the
+        // interpreter itself needs to be found
+        let commands = match &self.interpreter {
+            Some(interpreter) => vec![interpreter.into()],
+            None => self
+                .commands()
+                .into_iter()
+                .map(|cmd| self.expanded(cmd, false))
+                .collect(),
+        };
+        let coverage =
+            self.coverage.as_deref().map(|c| self.expanded(c, false));
+        // Guards are run as written, whether the test expands variables or not
+        let skip_if = self.skip_if.as_deref().map(Cow::from);
+
+        commands
+            .into_iter()
+            .chain(coverage)
+            .chain(skip_if)
+            .filter(|cmd| !cmd.trim().is_empty())
+            .collect()
+    }
+
+    /// Expands the environment variables in a command or directory of the test
+    /// if it sets `expand_env`, see [expand_vars].
+    ///
+    /// * `text`: command or directory to expand.
+    /// * `warn`: warns about variables which are not set.
+    fn expanded<'a>(&self, text: &'a str, warn: bool) -> Cow<'a, str> {
+        match self.expand_env {
+            true => expand_vars(text, &mut |var| {
+                if warn {
+                    log::warn!(
+                        "'{var}' is not set, expanding it to nothing in \
+                         '{text}'"
+                    );
+                }
+            })
+            .into(),
+            false => text.into(),
+        }
+    }
+
+    /// Returns the environment variables used by the commands and working
+    /// directory of the test which are not set, if it sets `expand_env`.
+    pub fn undefined_vars(&self) -> Vec<String> {
+        let mut undefined = Vec::new();
+        if self.expand_env {
+            for text in
+                self.commands().into_iter().chain(self.working_dir.as_deref())
+            {
+                expand_vars(text, &mut |var| undefined.push(var.to_string()));
+            }
+        }
+        undefined
+    }
+
+    /// Runs the `coverage` command of a test and checks the reported coverage
+    /// against `min_coverage`.
+    ///
+    /// Coverage is taken as the last percentage (`87.5%`) appearing in the
+    /// command's `stdout`. If no `min_coverage` is specified, any coverage is
+    /// accepted.
+    ///
+    /// * `coverage`: command reporting the coverage percentage.
+    /// * `stdout`: output of the test command, displayed on success.
+    /// * `ctx`: settings affecting how the coverage command is executed.
+    fn check_coverage(
+        &self,
+        coverage: &str,
+        stdout: String,
+        ctx: &ExecContext,
+    ) -> TestResult {
+        log::debug!("Measuring coverage: '{coverage}'");
+
+        let output = match execute(coverage, ctx) {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                return TestResult::Fail(
+                    String::from_utf8_lossy(&output.stderr).to_string(),
+                )
+            }
+            Err(err) => return TestResult::Fail(err.to_string()),
+        };
+
+        let report = String::from_utf8_lossy(&output.stdout);
+        let percentage = PERCENTAGE
+            .captures_iter(&report)
+            .last()
+            .and_then(|c| c[1].parse::<f64>().ok());
+
+        match (percentage, self.min_coverage) {
+            (None, _) => TestResult::Fail(format!(
+                "could not find a coverage percentage in:\n{report}"
+            )),
+            (Some(percentage), Some(min)) if percentage < min => {
+                TestResult::Fail(format!(
+                    "coverage {percentage:.2}% is below the required {min:.2}%"
+                ))
+            }
+            (Some(percentage), _) => {
+                TestResult::Pass(format!("{stdout}coverage: {percentage:.2}%"))
+            }
+        }
+    }
+}
+
+impl JsonCommandV1 {
+    /// Returns the command to run.
+    pub fn cmd(&self) -> &str {
+        match self {
+            JsonCommandV1::Plain(cmd) => cmd,
+            JsonCommandV1::WithMessage { cmd, .. } => cmd,
+        }
+    }
+
+    /// Returns the message to display if this command fails, if any.
+    pub fn message_on_fail(&self) -> Option<&str> {
+        match self {
+            JsonCommandV1::Plain(_) => None,
+            JsonCommandV1::WithMessage { message_on_fail, .. } => {
+                Some(message_on_fail)
+            }
+        }
+    }
+}
+
+/// Parses a version such as `1.80` or `1.80.1` into its major, minor and patch
+/// numbers, missing numbers being 0. Returns [None] if it is not a version.
+///
+/// * `version`: version to parse.
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut numbers = version.trim().split('.').map(str::parse::<u64>);
+    let major = numbers.next()?.ok()?;
+    let minor = numbers.next().unwrap_or(Ok(0)).ok()?;
+    let patch = numbers.next().unwrap_or(Ok(0)).ok()?;
+
+    numbers.next().is_none().then_some((major, minor, patch))
+}
+
+/// Returns the offset of the first byte at which two outputs differ, or [None]
+/// if they are identical. An output which is a prefix of the other differs at
+/// its end.
+///
+/// * `expected`: expected output.
+/// * `found`: actual output.
+fn first_difference(expected: &[u8], found: &[u8]) -> Option<usize> {
+    match expected.iter().zip(found).position(|(e, f)| e != f) {
+        Some(offset) => Some(offset),
+        None if expected.len() != found.len() => {
+            Some(expected.len().min(found.len()))
+        }
+        None => None,
+    }
+}
+
+/// Renders the differences between two outputs line by line, in the unified
+/// format of `diff -U`: removed lines are prefixed with `-`, added lines with
+/// `+`, and each group of changes is surrounded by up to `context` unchanged
+/// lines.
+///
+/// * `expected`: expected output.
+/// * `found`: actual output.
+/// * `context`: number of unchanged lines to display around each change.
+fn diff(expected: &str, found: &str, context: usize) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let found = found.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of the remaining lines, from
+    // which the shortest edit is read back
+    let mut lcs = vec![vec![0usize; found.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..found.len()).rev() {
+            lcs[i][j] = match expected[i] == found[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    // Each line along with its kind and its line number in either output
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < found.len() {
+        if i < expected.len() && j < found.len() && expected[i] == found[j] {
+            lines.push((' ', expected[i], i, j));
+            (i, j) = (i + 1, j + 1);
+        } else if j < found.len()
+            && (i == expected.len() || lcs[i][j + 1] >= lcs[i + 1][j])
+        {
+            lines.push(('+', found[j], i, j));
+            j += 1;
+        } else {
+            lines.push(('-', expected[i], i, j));
+            i += 1;
+        }
+    }
+
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (kind, ..))| *kind != ' ')
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    // Changes closer than twice the context share a hunk
+    let mut hunks = Vec::<(usize, usize)>::new();
+    for index in changes {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last)) if start <= *last => *last = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut rendered = Vec::new();
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        // Empty ranges are numbered after the line preceding them
+        let range = |kind: char, first: usize| {
+            let count =
+                hunk.iter().filter(|(k, ..)| *k == ' ' || *k == kind).count();
+            match count {
+                0 => format!("{first},0"),
+                count => format!("{},{count}", first + 1),
+            }
+        };
+        let (_, _, i, j) = hunk[0];
+
+        rendered.push(format!("@@ -{} +{} @@", range('-', i), range('+', j)));
+        rendered.extend(
+            hunk.iter().map(|(kind, line, ..)| format!("{kind}{line}")),
+        );
+    }
+
+    rendered.join("\n")
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// Finds which suite or test of a course is missing a required field.
+///
+/// Tests are deserialized one by one, then suites, then the course itself, so
+/// that the innermost object missing a field is the one reported. Returns
+/// [None] if no field is missing.
+///
+/// * `json`: raw JSON of the course.
+pub fn missing_field(json: &Value) -> Option<ParsingError> {
+    let field = |err: serde_json::Error| {
+        MISSING_FIELD.captures(&err.to_string()).map(|c| c["field"].to_string())
+    };
+    let name = |json: &Value, index: usize| match json.get("name") {
+        Some(Value::String(name)) => name.clone(),
+        _ => format!("#{}", index + 1),
+    };
+
+    let suites = json.get("suites").and_then(Value::as_array);
+    for (index_suite, suite) in suites.into_iter().flatten().enumerate() {
+        let tests = suite.get("tests").and_then(Value::as_array);
+        for (index_test, test) in tests.into_iter().flatten().enumerate() {
+            let err = serde_json::from_value::<JsonTestV1>(test.clone()).err();
+            if let Some(field) = err.and_then(field) {
+                return Some(ParsingError::MissingField {
+                    suite: Some(name(suite, index_suite)),
+                    test: Some(name(test, index_test)),
+                    field,
+                });
+            }
+        }
+
+        let err =
+            serde_json::from_value::<JsonTestSuiteV1>(suite.clone()).err();
+        if let Some(field) = err.and_then(field) {
+            return Some(ParsingError::MissingField {
+                suite: Some(name(suite, index_suite)),
+                test: None,
+                field,
+            });
+        }
+    }
+
+    let err = serde_json::from_value::<JsonCourseV1>(json.clone()).err();
+    err.and_then(field).map(|field| ParsingError::MissingField {
+        suite: None,
+        test: None,
+        field,
+    })
+}
+
+/// Finds a field of a course, suite or test which is not part of the course
+/// format, such as a misspelled `massage_on_fail`.
+///
+/// Known fields are taken from [JsonCourseV1::schema]. Returns [None] if every
+/// field is known.
+///
+/// * `json`: raw JSON of the course.
+pub fn unknown_field(json: &Value) -> Option<ParsingError> {
+    let schema = JsonCourseV1::schema();
+    let unknown = |json: &Value, schema: &Value| {
+        let known = schema.get("properties")?.as_object()?;
+        json.as_object()?.keys().find(|key| !known.contains_key(*key)).cloned()
+    };
+    let name = |json: &Value, index: usize| match json.get("name") {
+        Some(Value::String(name)) => name.clone(),
+        _ => format!("#{}", index + 1),
+    };
+    let error = |field: String, suite: Option<String>, test| {
+        ParsingError::CourseFmtError(format!(
+            "unknown field '{field}'{}",
+            super::location(&suite, &test)
+        ))
+    };
+
+    if let Some(field) = unknown(json, &schema) {
+        return Some(error(field, None, None));
+    }
+
+    let suites = json.get("suites").and_then(Value::as_array);
+    for (index_suite, suite) in suites.into_iter().flatten().enumerate() {
+        if let Some(field) = unknown(suite, &schema["$defs"]["suite"]) {
+            return Some(error(field, Some(name(suite, index_suite)), None));
+        }
+
+        let tests = suite.get("tests").and_then(Value::as_array);
+        for (index_test, test) in tests.into_iter().flatten().enumerate() {
+            if let Some(field) = unknown(test, &schema["$defs"]["test"]) {
+                return Some(error(
+                    field,
+                    Some(name(suite, index_suite)),
+                    Some(name(test, index_test)),
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::test_utils::{course, suite, test};
+
+    /// A problem affecting the test `test` of the suite `suite`.
+    fn problem(
+        suite: &str,
+        test: Option<&str>,
+        message: &str,
+    ) -> ValidationError {
+        ValidationError {
+            suite: Some(suite.to_string()),
+            test: test.map(str::to_string),
+            message: message.to_string(),
+        }
+    }
+
+    /// Names of the suites of `course` and of their tests, in order.
+    fn names(course: &JsonCourseV1) -> Vec<(&str, Vec<&str>)> {
+        course
+            .suites
+            .iter()
+            .map(|suite| {
+                let tests = suite.tests.iter().map(|t| t.name.as_str());
+                (suite.name.as_str(), tests.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sort_keeps_array_order() {
+        let mut course = course(json!([
+            suite("B", json!([test("2", "true"), test("1", "true")])),
+            suite("A", json!([test("1", "true"), test("2", "true")])),
+        ]));
+
+        course.sort();
+        assert_eq!(
+            names(&course),
+            [("B", vec!["2", "1"]), ("A", vec!["1", "2"])]
+        );
+    }
+
+    #[test]
+    fn sort_follows_order_field() {
+        let mut first = suite("First", json!([test("A", "true")]));
+        first["order"] = json!(1);
+        let mut late = test("Late", "true");
+        late["order"] = json!(2);
+        let mut early = test("Early", "true");
+        early["order"] = json!(-1);
+        let mut course = course(json!([
+            first,
+            suite("Second", json!([late, test("A", "true"), early])),
+        ]));
+
+        // Elements without an order count as 0 and keep their position
+        course.sort();
+        assert_eq!(
+            names(&course),
+            [("Second", vec!["Early", "A", "Late"]), ("First", vec!["A"]),]
+        );
+    }
+
+    #[test]
+    fn missing_binaries_include_checks() {
+        let mut checked = test("A", "true");
+        checked["skip_if"] = json!("missing-guard --quiet");
+        checked["coverage"] = json!("missing-coverage");
+        let course = course(json!([suite(
+            "Suite",
+            json!([
+                checked,
+                test("B", "missing-cmd"),
+                test("C", "missing-cmd")
+            ])
+        )]));
+
+        assert_eq!(
+            course.missing_binaries(),
+            ["missing-coverage", "missing-guard", "missing-cmd"]
+        );
+    }
+
+    #[test]
+    fn validate_accepts_valid_course() {
+        let course =
+            course(json!([suite("Suite", json!([test("A", "true")]))]));
+
+        assert_eq!(course.validate(), []);
+    }
+
+    #[test]
+    fn validate_reports_empty_command() {
+        let course = course(json!([suite(
+            "Suite",
+            json!([test("A", "true"), test("B", "  ")])
+        )]));
+
+        assert_eq!(
+            course.validate(),
+            [problem("Suite", Some("B"), "no `cmd` or `cmds` to run")]
+        );
+    }
+
+    #[test]
+    fn validate_reports_duplicate_names() {
+        let course = course(json!([
+            suite("Suite", json!([test("A", "true"), test("A", "true")])),
+            suite("Suite", json!([test("B", "true")])),
+        ]));
+
+        assert_eq!(
+            course.validate(),
+            [
+                problem(
+                    "Suite",
+                    Some("A"),
+                    "another test of the suite has this name"
+                ),
+                problem(
+                    "Suite",
+                    None,
+                    "another suite of the course has this name"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_name_reports_first_duplicate() {
+        let unique = course(json!([
+            suite("First", json!([test("A", "true")])),
+            suite("Second", json!([test("A", "true")])),
+        ]));
+        let duplicated = course(json!([
+            suite("First", json!([test("A", "true"), test("A", "true")])),
+            suite("First", json!([test("B", "true")])),
+        ]));
+
+        assert!(unique.duplicate_name().is_none());
+        assert_eq!(
+            duplicated.duplicate_name().unwrap().to_string(),
+            "duplicate name 'A' in suite 'First'"
+        );
+    }
+
+    #[test]
+    fn validate_reports_unknown_required_suite() {
+        let mut required = suite("Second", json!([test("A", "true")]));
+        required["requires_suite"] = json!("Missing");
+        let course = course(json!([
+            suite("First", json!([test("A", "true")])),
+            required,
+        ]));
+
+        assert_eq!(
+            course.validate(),
+            [problem(
+                "Second",
+                None,
+                "`requires_suite` 'Missing' is not a suite run before it"
+            )]
+        );
+    }
+
+    #[test]
+    fn validate_reports_invalid_patterns() {
+        let mut redacted = test("A", "true");
+        redacted["redact"] = json!(["regex:("]);
+        let mut asserted = test("B", "true");
+        asserted["assertions"] =
+            json!([{ "name": "output", "stdout_matches": "[" }]);
+        let course =
+            course(json!([suite("Suite", json!([redacted, asserted]))]));
+
+        let problems = course.validate();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].test.as_deref(), Some("A"));
+        assert!(problems[0].message.starts_with("invalid pattern in `redact`"));
+        assert_eq!(problems[1].test.as_deref(), Some("B"));
+        assert!(problems[1]
+            .message
+            .starts_with("assertion 'output' has an invalid pattern"));
+    }
+
+    #[test]
+    fn binary_output_is_compared_past_the_output_limit() {
+        let mut test = serde_json::from_value::<JsonTestV1>(test(
+            "A",
+            "printf 0123456789",
+        ))
+        .unwrap();
+        test.binary_stdout = true;
+        test.expected_bytes = Some(b"0123456789".to_vec());
+        let ctx = ExecContext { max_output: Some(4), ..Default::default() };
+
+        match test.run(&ctx) {
+            TestResult::Pass(output) => {
+                assert_eq!(output, "10 bytes of output match")
             }
+            _ => panic!("binary output was truncated"),
         }
     }
 }
@@ -0,0 +1,89 @@
+//! A module for providing tests with fixture files.
+//!
+//! Fixtures are copied into the current directory before a test runs and
+//! removed once it has finished, so that input files needed by tests do not
+//! have to be committed to the student's repository.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Fixture files copied into the current directory, removed when dropped.
+///
+/// Files which already exist are never overwritten nor removed, so that
+/// fixtures cannot destroy the work of a student.
+///
+/// * `created`: files and directories created by copying the fixtures, in
+///   order of creation.
+#[derive(Default)]
+pub struct Fixtures {
+    created: Vec<PathBuf>,
+}
+
+impl Fixtures {
+    /// Copies the contents of a fixture directory into the current directory.
+    ///
+    /// Any fixture copied before an error occurs is removed again.
+    ///
+    /// * `dir`: directory holding the fixtures, nothing is copied if [None].
+    pub fn install(dir: Option<&Path>) -> Result<Self, String> {
+        let mut fixtures = Self::default();
+
+        if let Some(dir) = dir {
+            log::debug!("Copying fixtures from '{}'", dir.display());
+            fixtures.copy(dir, Path::new(".")).map_err(|err| {
+                format!("could not copy fixtures from {}: {err}", dir.display())
+            })?;
+        }
+
+        Ok(fixtures)
+    }
+
+    /// Recursively copies the contents of a directory into another.
+    ///
+    /// * `from`: directory to copy the contents of.
+    /// * `to`: directory to copy them into.
+    fn copy(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            let source = entry.path();
+            let target = to.join(entry.file_name());
+
+            match (entry.file_type()?.is_dir(), target.exists()) {
+                (true, true) => self.copy(&source, &target)?,
+                (true, false) => {
+                    std::fs::create_dir(&target)?;
+                    self.created.push(target.clone());
+                    self.copy(&source, &target)?;
+                }
+                (false, true) => log::warn!(
+                    "Not overwriting {} with a fixture",
+                    target.display()
+                ),
+                (false, false) => {
+                    std::fs::copy(&source, &target)?;
+                    self.created.push(target);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Fixtures {
+    fn drop(&mut self) {
+        // Files are removed before the directories containing them
+        for path in self.created.iter().rev() {
+            let removed = match path.is_dir() {
+                true => std::fs::remove_dir(path),
+                false => std::fs::remove_file(path),
+            };
+
+            if let Err(err) = removed {
+                log::debug!("Could not remove {}: {err}", path.display());
+            }
+        }
+    }
+}
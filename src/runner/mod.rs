@@ -1,22 +1,58 @@
-use std::time::Duration;
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::BTreeMap,
+    io::{IsTerminal, Write},
+    ops::Deref,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use indicatif::{ProgressBar, ProgressStyle};
-
-use colored::Colorize;
 use lazy_static::lazy_static;
+
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
 use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::parsing::{load_course, JsonCourseVersion, ParsingError};
+use crate::{
+    locale::{Catalog, Locale},
+    parsing::{
+        exec::{Background, ExecContext, ResourceUsage},
+        v1::parse_version,
+        CourseSource, JsonCourseVersion, LoadOptions, ParsingError, SkipReason,
+        Test, TestResult,
+    },
+};
 
-use self::v1::TestRunnerV1;
+use self::{recording::Recording, state::RunState, v1::TestRunnerV1};
 
+mod fixtures;
+pub mod recording;
+pub mod state;
+// The derived constructor of runners takes every piece of their state
+#[allow(clippy::too_many_arguments)]
 mod v1;
 
+/// How long a test can run before the progress bar switches to a spinner.
+const SPINNER_DELAY: Duration = Duration::from_millis(500);
+
+/// Banner displayed at the start of every run.
+const DOTCODESCHOOL: &str = "[ DotCodeSchool CLI ]";
+
 lazy_static! {
-    static ref DOTCODESCHOOL: String =
-        "[ DotCodeSchool CLI ]".bold().truecolor(230, 0, 122).to_string();
-    static ref OPTIONAL: String =
-        "(optional)".white().dimmed().italic().to_string();
+    /// ANSI escape sequences, such as colors (CSI) and window titles (OSC).
+    static ref ANSI_ESCAPE: Regex = Regex::new(
+        r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-_]"
+    )
+    .unwrap();
 }
 
 #[derive(Eq, PartialEq, Clone)]
@@ -30,6 +66,620 @@ pub enum TestRunnerState {
     Finish,
 }
 
+impl TestRunnerState {
+    /// Every transition a [Runner] can make, as the names of the states it
+    /// goes from and to along with the condition for taking it.
+    pub const TRANSITIONS: &'static [(
+        &'static str,
+        &'static str,
+        &'static str,
+    )] = &[
+        ("Loaded", "Update", ""),
+        ("Loaded", "Failed", "pre-flight check failed"),
+        ("Update", "NewSuite", "first suite"),
+        ("Update", "Passed", "no suites"),
+        ("NewSuite", "NewTest", "first test"),
+        ("NewSuite", "NewSuite", "empty suite"),
+        ("NewSuite", "Passed", "empty last suite"),
+        ("NewTest", "NewTest", "next test"),
+        ("NewTest", "NewSuite", "next suite"),
+        ("NewTest", "Passed", "last test"),
+        ("NewTest", "Failed", "mandatory test failed"),
+        ("Failed", "Finish", ""),
+        ("Passed", "Finish", ""),
+        ("Finish", "Finish", ""),
+    ];
+
+    /// Returns the name of this state, without its data.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestRunnerState::Loaded => "Loaded",
+            TestRunnerState::Update => "Update",
+            TestRunnerState::NewSuite(_) => "NewSuite",
+            TestRunnerState::NewTest(_, _) => "NewTest",
+            TestRunnerState::Failed(_) => "Failed",
+            TestRunnerState::Passed => "Passed",
+            TestRunnerState::Finish => "Finish",
+        }
+    }
+
+    /// Whether a [Runner] can go from this state to `next`, according to
+    /// [TestRunnerState::TRANSITIONS].
+    ///
+    /// * `next`: state the runner went to.
+    pub fn can_transition(&self, next: &Self) -> bool {
+        Self::TRANSITIONS
+            .iter()
+            .any(|(from, to, _)| *from == self.name() && *to == next.name())
+    }
+
+    /// Renders [TestRunnerState::TRANSITIONS] as a Graphviz DOT graph.
+    pub fn to_dot() -> String {
+        let edges = Self::TRANSITIONS
+            .iter()
+            .map(|(from, to, label)| {
+                format!("    {from} -> {to} [label=\"{label}\"];\n")
+            })
+            .collect::<String>();
+
+        format!(
+            "digraph TestRunnerState {{\n    Loaded [shape=doublecircle];\n    \
+             Finish [shape=doublecircle];\n{edges}}}\n"
+        )
+    }
+}
+
+/// Why a run ended, each reason exiting with its own code so that autograders
+/// can tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitReason {
+    /// Every mandatory test passed.
+    Passed = 0,
+    /// A mandatory test failed.
+    TestFailed = 1,
+    /// The course could not be loaded, so no test was run.
+    LoadFailed = 2,
+    /// A mandatory test failed by exceeding its timeout.
+    TimedOut = 3,
+    /// A check made before running any test failed, such as the toolchain
+    /// being too old.
+    CheckFailed = 4,
+    /// No course file was given and none could be found, as on the very first
+    /// run.
+    NoCourse = 5,
+}
+
+impl ExitReason {
+    /// Returns the exit code of the process for this reason.
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Settings affecting how a [Runner] executes and displays a course.
+#[derive(Clone, Debug, Default)]
+pub struct RunnerOptions {
+    /// Language used for user-facing messages.
+    pub locale: Locale,
+    /// Suppresses all output except for the final score.
+    pub score_only: bool,
+    /// Replaces all output with a JSON [Summary] of the run.
+    pub json: bool,
+    /// Replaces emojis and box-drawing characters with plain ASCII.
+    pub ascii: bool,
+    /// Colors and styles used for output.
+    pub theme: ThemeName,
+    /// Displays test output live as it is produced instead of once the test
+    /// has finished.
+    pub stream: bool,
+    /// How long to wait after asking a timed out test to terminate before
+    /// killing it.
+    pub kill_grace: Duration,
+    /// Maximum number of bytes captured from each output stream of a test,
+    /// [None] for no limit.
+    pub max_output: Option<usize>,
+    /// Maximum number of bytes of output buffered by all the tests running in
+    /// parallel, above which no new test is started until some finish. [None]
+    /// for no limit.
+    pub max_concurrent_output: Option<usize>,
+    /// Number of characters after which lines of test output are truncated,
+    /// 0 for no limit.
+    pub max_line_length: usize,
+    /// Where to write a Markdown report of the run, if anywhere.
+    pub markdown: Option<PathBuf>,
+    /// Where to write a badge of the score of the run, if anywhere. Written
+    /// as an SVG image if it ends in `.svg`, as a shields.io endpoint
+    /// otherwise.
+    pub badge: Option<PathBuf>,
+    /// Where to write the JSON [Summary] of the run, if anywhere. Unlike
+    /// `json`, this does not replace the output of the run.
+    pub summary_json_path: Option<PathBuf>,
+    /// Holds back the output of tests until the end of the run, where it is
+    /// displayed grouped by status.
+    pub buffered: bool,
+    /// Displays each test on a single line instead of in a box, the output of
+    /// tests only being displayed when they fail.
+    pub compact: bool,
+    /// Whether colors and other ANSI escape sequences printed by tests are kept
+    /// when displaying their output.
+    pub color_passthrough: ColorPassthrough,
+    /// Hides the progress bar while still displaying the output of tests.
+    pub no_progress: bool,
+    /// Skips optional tests once every mandatory test has passed.
+    pub skip_trailing_optional: bool,
+    /// Skips every optional test.
+    pub skip_optional: bool,
+    /// Leaves optional tests out of the score, counting those which passed as
+    /// a bonus instead, see [Summary::without_optional].
+    pub no_optional_in_score: bool,
+    /// Shuffles the tests of each suite in an order derived from this string,
+    /// such as a student ID, see [crate::parsing::v1::JsonCourseV1::shuffle].
+    pub exam_seed: Option<String>,
+    /// Fails mandatory tests which are skipped instead of leaving them out of
+    /// the score.
+    pub fail_on_skip: bool,
+    /// State of a previous run, whose failed tests are the only ones to be
+    /// run again. Tests which passed or were skipped keep their status.
+    pub retry: Option<RunState>,
+    /// State of a previous run which was interrupted, whose tests keep their
+    /// status so that the run continues from where it stopped.
+    pub resume: Option<RunState>,
+    /// Where to save the state of the run as each test finishes, for use by
+    /// `retry-failed` and `--continue`. Nothing is saved when unset.
+    pub state_path: Option<PathBuf>,
+    /// Format version to load the course as, regardless of the version it
+    /// declares.
+    pub force_version: Option<String>,
+    /// Rejects courses containing fields which are not part of the course
+    /// format, in which suites or tests share a name, or which use environment
+    /// variables that are not set.
+    pub strict_json: bool,
+    /// Maximum number of tests of a suite run at the same time, 1 or less to
+    /// run tests one by one.
+    pub jobs: usize,
+    /// Maximum number of suites run at the same time, 1 or less to run suites
+    /// one by one.
+    pub jobs_suite: usize,
+    /// Directory whose contents are copied into the current directory while
+    /// each test runs, if any.
+    pub fixtures: Option<PathBuf>,
+    /// Binaries which tests are allowed to run, for courses which are not
+    /// trusted. Any binary can be run if [None].
+    pub allowed_cmds: Option<Vec<String>>,
+    /// Fails tests which take longer than their `expected_duration_ms`
+    /// instead of only warning about them.
+    pub strict_perf: bool,
+    /// Number of slowest tests to list at the end of the run, if any.
+    pub profile: Option<usize>,
+    /// Where to save a recording of the output of the run, if anywhere.
+    pub record: Option<PathBuf>,
+    /// Directory which the `working_dir` of tests is relative to, the current
+    /// directory if [None].
+    pub root: Option<PathBuf>,
+    /// Captures the `stderr` of tests along with their `stdout`, in the order
+    /// in which it was written.
+    pub merge_output: bool,
+    /// Number of unchanged lines displayed around each change of the diff of
+    /// an unexpected output.
+    pub diff_context: usize,
+    /// Runs tests attached to a pseudo-terminal, so that tools which only use
+    /// colors in a terminal still do.
+    pub pty: bool,
+    /// Fails the run instead of warning when the toolchain is older than the
+    /// `min_rust_version` of the course.
+    pub strict_toolchain: bool,
+    /// Checks that the binary of every test command can be found before
+    /// running any test, failing the run if any is missing.
+    pub preflight: bool,
+    /// Displays how many tests have run in the title of the terminal.
+    pub title_progress: bool,
+    /// Name or position of the suite to start the run at, earlier suites being
+    /// skipped.
+    pub start_at: Option<String>,
+    /// Version of the course whose tests, and those of later versions, are
+    /// highlighted as new.
+    pub highlight_new: Option<String>,
+    /// Displays how each test contributed to the score at the end of the run.
+    pub explain_scoring: bool,
+    /// Extra arguments appended to the commands of every test.
+    pub args: Vec<String>,
+    /// How to group the pass rates displayed at the end of the run, if at all.
+    pub group_by: Option<GroupBy>,
+}
+
+/// Where the human-readable output of a [Runner] is displayed.
+///
+/// Output is displayed above the progress bar by default. When given a
+/// writer, output is written to it directly instead and the progress bar is
+/// hidden, as `indicatif` does not display anything above a hidden bar.
+///
+/// Dereferences to the underlying [ProgressBar] so that it can be updated as
+/// tests run.
+pub struct Console {
+    progress: ProgressBar,
+    writer: Option<Mutex<Box<dyn Write + Send>>>,
+    recording: Option<Mutex<Recording>>,
+    terminal: bool,
+}
+
+impl Console {
+    /// Displays output above a progress bar.
+    ///
+    /// * `progress`: progress bar of the run.
+    pub fn new(progress: ProgressBar) -> Self {
+        // The progress bar is drawn on `stderr`, along with the output above it
+        let terminal = std::io::stderr().is_terminal();
+        Self { progress, writer: None, recording: None, terminal }
+    }
+
+    /// Writes output to `writer`, without any progress bar.
+    ///
+    /// * `writer`: where to write output.
+    pub fn with_writer(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            progress: ProgressBar::hidden(),
+            writer: Some(Mutex::new(writer)),
+            recording: None,
+            terminal: false,
+        }
+    }
+
+    /// Writes output to `stdout`, without any progress bar.
+    pub fn stdout() -> Self {
+        Self {
+            terminal: std::io::stdout().is_terminal(),
+            ..Self::with_writer(Box::new(std::io::stdout()))
+        }
+    }
+
+    /// Whether output is displayed in a terminal, rather than written to a
+    /// file, a pipe or any other writer.
+    pub fn is_terminal(&self) -> bool {
+        self.terminal
+    }
+
+    /// Also records every line of output as it is displayed, see
+    /// [Console::save_recording].
+    pub fn recorded(self) -> Self {
+        Self { recording: Some(Mutex::new(Recording::default())), ..self }
+    }
+
+    /// Saves the output recorded so far, if it is being recorded.
+    ///
+    /// * `path`: file to save the recording to.
+    pub fn save_recording(&self, path: &Path) {
+        if let Some(recording) = &self.recording {
+            recording.lock().unwrap().save(path);
+        }
+    }
+
+    /// Discards all output.
+    pub fn silent() -> Self {
+        Self::new(ProgressBar::hidden())
+    }
+
+    /// Displays a line of output.
+    ///
+    /// * `msg`: line to display.
+    pub fn println<I: AsRef<str>>(&self, msg: I) {
+        if let Some(recording) = &self.recording {
+            recording.lock().unwrap().record(msg.as_ref());
+        }
+
+        match &self.writer {
+            Some(writer) => {
+                let mut writer = writer.lock().unwrap();
+                let _ = writeln!(writer, "{}", msg.as_ref());
+            }
+            None => self.progress.println(msg),
+        }
+    }
+}
+
+impl Deref for Console {
+    type Target = ProgressBar;
+
+    fn deref(&self) -> &Self::Target {
+        &self.progress
+    }
+}
+
+/// Markers used to decorate the output of a [Runner].
+pub struct Glyphs {
+    pub course: &'static str,
+    pub exercises: &'static str,
+    pub warning: &'static str,
+    pub updated: &'static str,
+    pub test: &'static str,
+    pub pass: &'static str,
+    pub fail: &'static str,
+    pub skip: &'static str,
+    pub finish: &'static str,
+    pub bonus: &'static str,
+    pub time: &'static str,
+    pub box_top: &'static str,
+    pub box_side: &'static str,
+    pub box_bottom: &'static str,
+    pub bar: &'static str,
+    pub spinner: &'static str,
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    course: "🎓",
+    exercises: "📒",
+    warning: "⚠",
+    updated: "📝",
+    test: "🧪",
+    pass: "✅",
+    fail: "❌",
+    skip: "⏭",
+    finish: "🏁",
+    bonus: "💡",
+    time: "⏱",
+    box_top: "╭─",
+    box_side: "│",
+    box_bottom: "╰─",
+    bar: "█░",
+    spinner: "⠁⠂⠄⡀⢀⠠⠐⠈ ",
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    course: "[COURSE]",
+    exercises: "[INFO]",
+    warning: "[WARN]",
+    updated: "[INFO]",
+    test: "[TEST]",
+    pass: "[PASS]",
+    fail: "[FAIL]",
+    skip: "[SKIP]",
+    finish: "[DONE]",
+    bonus: "[BONUS]",
+    time: "[TIME]",
+    box_top: "+--",
+    box_side: "|",
+    box_bottom: "+--",
+    bar: "#-",
+    spinner: "|/-\\ ",
+};
+
+impl Glyphs {
+    /// Returns the [ASCII] markers if `ascii` is set, the [UNICODE] ones
+    /// otherwise.
+    ///
+    /// * `ascii`: whether the terminal does not render emojis or box-drawing
+    ///   characters well.
+    pub fn select(ascii: bool) -> &'static Glyphs {
+        match ascii {
+            true => &ASCII,
+            false => &UNICODE,
+        }
+    }
+}
+
+/// Colors and text styles used in the output of a [Runner].
+///
+/// * `banner`: DotCodeSchool banner.
+/// * `heading`: name and instructor of the course.
+/// * `suite`: name of each suite.
+/// * `emphasis`: names of tests and other highlighted values.
+/// * `muted`: secondary information, such as descriptions or spinner messages.
+/// * `optional`: label of optional suites and tests.
+/// * `skip`: reason a test was skipped.
+/// * `pass`: final score.
+/// * `fail`: output of failed tests.
+/// * `error`: reason a run failed.
+pub struct Theme {
+    pub banner: fn(&str) -> ColoredString,
+    pub heading: fn(&str) -> ColoredString,
+    pub suite: fn(&str) -> ColoredString,
+    pub emphasis: fn(&str) -> ColoredString,
+    pub muted: fn(&str) -> ColoredString,
+    pub optional: fn(&str) -> ColoredString,
+    pub skip: fn(&str) -> ColoredString,
+    pub pass: fn(&str) -> ColoredString,
+    pub fail: fn(&str) -> ColoredString,
+    pub error: fn(&str) -> ColoredString,
+}
+
+pub const DEFAULT: Theme = Theme {
+    banner: |s| s.bold().truecolor(230, 0, 122),
+    heading: |s| s.white().bold(),
+    suite: |s| s.bold().green(),
+    emphasis: |s| s.bold(),
+    muted: |s| s.italic().dimmed(),
+    optional: |s| s.white().dimmed().italic(),
+    skip: |s| s.dimmed(),
+    pass: |s| s.green().bold(),
+    fail: |s| s.red().dimmed(),
+    error: |s| s.red().bold(),
+};
+
+/// Avoids dimmed text and uses bright colors, for low-vision users.
+pub const HIGH_CONTRAST: Theme = Theme {
+    banner: |s| s.bold().bright_magenta(),
+    heading: |s| s.bright_white().bold(),
+    suite: |s| s.bold().bright_green().underline(),
+    emphasis: |s| s.bold().bright_white(),
+    muted: |s| s.bright_white(),
+    optional: |s| s.bright_yellow().italic(),
+    skip: |s| s.bright_white(),
+    pass: |s| s.bright_green().bold(),
+    fail: |s| s.bright_red(),
+    error: |s| s.bright_red().bold().underline(),
+};
+
+/// Only uses text styles, for terminals without colors.
+pub const MONOCHROME: Theme = Theme {
+    banner: |s| s.bold(),
+    heading: |s| s.bold(),
+    suite: |s| s.bold().underline(),
+    emphasis: |s| s.bold(),
+    muted: |s| s.italic(),
+    optional: |s| s.italic(),
+    skip: |s| s.italic(),
+    pass: |s| s.bold(),
+    fail: |s| s.normal(),
+    error: |s| s.bold().underline(),
+};
+
+/// Built-in [Theme]s selectable with `--theme`.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    HighContrast,
+    Monochrome,
+}
+
+/// Whether ANSI escape sequences printed by tests, such as colors, are kept
+/// when displaying their output, selected with `--color-output-passthrough`.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorPassthrough {
+    /// Keeps escape sequences when output is displayed in a terminal, and
+    /// strips them otherwise such as when writing to a log file.
+    #[default]
+    Auto,
+    Keep,
+    Strip,
+}
+
+/// Ways of grouping results at the end of a run with `--group-by`.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupBy {
+    Suite,
+    Tag,
+}
+
+impl RunnerOptions {
+    /// Strips ANSI escape sequences from test output, unless they are to be
+    /// kept according to [RunnerOptions::color_passthrough].
+    ///
+    /// * `output`: output of a test.
+    /// * `console`: where the output is displayed.
+    pub fn child_colors<'a>(
+        &self,
+        output: &'a str,
+        console: &Console,
+    ) -> Cow<'a, str> {
+        let keep = match self.color_passthrough {
+            ColorPassthrough::Auto => console.is_terminal(),
+            ColorPassthrough::Keep => true,
+            ColorPassthrough::Strip => false,
+        };
+
+        match keep {
+            true => output.into(),
+            false => ANSI_ESCAPE.replace_all(output, ""),
+        }
+    }
+
+    /// Returns the colors and styles to display output with.
+    pub fn theme(&self) -> &'static Theme {
+        match self.theme {
+            ThemeName::Default => &DEFAULT,
+            ThemeName::HighContrast => &HIGH_CONTRAST,
+            ThemeName::Monochrome => &MONOCHROME,
+        }
+    }
+
+    /// Returns the markers to decorate output with, [ASCII] markers being
+    /// used for terminals which do not render emojis or box-drawing
+    /// characters well.
+    pub fn glyphs(&self) -> &'static Glyphs {
+        Glyphs::select(self.ascii)
+    }
+
+    /// Returns the settings affecting how the course is loaded.
+    pub fn load_options(&self) -> LoadOptions<'_> {
+        LoadOptions {
+            force_version: self.force_version.as_deref(),
+            strict: self.strict_json,
+        }
+    }
+}
+
+/// Status of a test once it has been run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Result of running a single test, accumulated by the [Runner] over the
+/// course of a run.
+///
+/// * `id`: position of the test in the course, such as `3.2` for the second
+///   test of the third suite.
+/// * `suite`: name of the suite the test belongs to.
+/// * `test`: name of the test.
+/// * `mandatory`: whether the test must pass for the course to be validated.
+/// * `status`: whether the test passed, failed or was skipped.
+/// * `output`: test output, or the reason it was skipped.
+/// * `duration`: time taken to run the test.
+/// * `usage`: resources used by the commands of the test, only measured on
+///   Unix.
+/// * `timed_out`: whether a command of the test exceeded its timeout.
+/// * `skip_reason`: why the test was skipped, if it was.
+/// * `tags`: skills checked by the test.
+/// * `attempts`: number of times the test was run, more than once if it was
+///   retried after failing.
+/// * `flaky`: whether the test passed only after being retried.
+/// * `rendered`: output of the test as displayed by the runner.
+#[derive(Serialize, Clone, Debug)]
+pub struct TestOutcome {
+    pub id: String,
+    pub suite: String,
+    pub test: String,
+    pub mandatory: bool,
+    pub status: TestStatus,
+    pub output: String,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<SkipReason>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "is_single")]
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub flaky: bool,
+    #[serde(skip)]
+    pub rendered: String,
+}
+
+/// Machine-readable summary of a run, displayed with `--json`.
+///
+/// Summaries can be produced whether or not the run succeeded, in which case
+/// `tests` only holds the results of the tests which were run so far.
+#[derive(Serialize, Debug)]
+pub struct Summary<'a> {
+    pub course: &'a str,
+    pub result: TestStatus,
+    pub score: f64,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_test: Option<&'a str>,
+    /// Number of optional tests which passed, when they are left out of the
+    /// score and only count as a bonus, see [Summary::without_optional].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bonus: Option<usize>,
+    pub tests: &'a [TestOutcome],
+}
+
+// Only one runner exists at a time so its size does not matter
+#[allow(clippy::large_enum_variant)]
 pub enum RunnerVersion {
     V1(TestRunnerV1),
     Undefined,
@@ -50,7 +700,9 @@ pub trait Runner {
     /// - [TestRunnerState::Passed]: **all** mandatory tests passed.
     /// - [TestRunnerState::Finish]: finished execution.
     ///
-    /// TODO: state diagram
+    /// Transitions between states are listed in
+    /// [TestRunnerState::TRANSITIONS], which `--print-state-diagram` renders
+    /// as a graph.
     fn run(self) -> Self;
 
     /// Returns the current state of the [Runner]
@@ -68,50 +720,162 @@ pub trait Runner {
     /// - [TestRunnerState::Finish]: finished execution.
     fn state(&self) -> TestRunnerState;
 
+    /// Returns the results of the tests run so far, in order.
+    fn results(&self) -> &[TestOutcome];
+
     /// Creates a new [Runner] instance depending on the version specified in
     /// `tests.json`.
     ///
-    /// * `path`: path to `tests.json`.
-    fn new(path: &str) -> RunnerVersion {
-        match load_course(path) {
-            Ok(course_version) => match course_version {
-                JsonCourseVersion::V1(course) => {
-                    let test_count = course
-                        .suites
-                        .iter()
-                        .fold(0, |acc, suite| acc + suite.tests.len());
-
-                    let progress = ProgressBar::new(test_count as u64);
-
-                    let runner = TestRunnerV1::new(
-                        progress,
-                        0,
-                        TestRunnerState::Loaded,
-                        course,
-                    );
-
-                    RunnerVersion::V1(runner)
+    /// * `source`: where to load the course from.
+    /// * `options`: settings affecting how the course is run.
+    #[allow(clippy::new_ret_no_self)]
+    fn new(source: &CourseSource, options: RunnerOptions) -> RunnerVersion {
+        match source.load_with(&options.load_options()) {
+            Ok(course) => RunnerVersion::from_course(course, options),
+            // Displayed even in quiet modes so that students do not mistake
+            // a run which never happened for a successful one
+            Err(err) => {
+                let msg = options.locale.catalog();
+                report_load_error(&err, msg.load_failed, &options);
+                RunnerVersion::Undefined
+            }
+        }
+    }
+}
+
+impl RunnerVersion {
+    /// Returns why the run ended, as of the tests run so far.
+    pub fn exit_reason(&self) -> ExitReason {
+        let failed = self
+            .results()
+            .iter()
+            .find(|r| r.mandatory && r.status == TestStatus::Failed);
+
+        match (self, failed) {
+            (RunnerVersion::Undefined, _) => ExitReason::LoadFailed,
+            (RunnerVersion::V1(runner), _) if runner.aborted => {
+                ExitReason::CheckFailed
+            }
+            (_, Some(outcome)) if outcome.timed_out => ExitReason::TimedOut,
+            (_, Some(_)) => ExitReason::TestFailed,
+            (_, None) => ExitReason::Passed,
+        }
+    }
+
+    /// Creates a new [Runner] instance for a course which has already been
+    /// loaded, such as one kept from a previous run.
+    ///
+    /// * `course`: course to run.
+    /// * `options`: settings affecting how the course is run.
+    pub fn from_course(
+        course: JsonCourseVersion,
+        options: RunnerOptions,
+    ) -> RunnerVersion {
+        let JsonCourseVersion::V1(v1) = &course;
+        let progress =
+            match (options.score_only || options.json, options.no_progress) {
+                (true, _) => Console::silent(),
+                (false, true) => Console::stdout(),
+                (false, false) => {
+                    Console::new(ProgressBar::new(v1.test_count() as u64))
                 }
-            },
-            Err(e) => {
-                let msg = match e {
-                    ParsingError::CourseFmtError(msg) => msg,
-                    ParsingError::FileOpenError(msg) => msg,
+            };
+
+        Self::with_console(course, options, progress)
+    }
+
+    /// Creates a new [Runner] instance for a course which has already been
+    /// loaded, writing its output to `writer` instead of the terminal, such
+    /// as a buffer when embedding the runner or testing its output.
+    ///
+    /// Machine-readable output such as `--json` is still printed on `stdout`.
+    ///
+    /// * `course`: course to run.
+    /// * `options`: settings affecting how the course is run.
+    /// * `writer`: where to write the output of the run.
+    pub fn from_course_with_writer(
+        course: JsonCourseVersion,
+        options: RunnerOptions,
+        writer: Box<dyn Write + Send>,
+    ) -> RunnerVersion {
+        Self::with_console(course, options, Console::with_writer(writer))
+    }
+
+    /// Creates a new [Runner] instance displaying its output on `progress`.
+    ///
+    /// * `course`: course to run.
+    /// * `options`: settings affecting how the course is run.
+    /// * `progress`: where to display the output of the run.
+    fn with_console(
+        course: JsonCourseVersion,
+        options: RunnerOptions,
+        progress: Console,
+    ) -> RunnerVersion {
+        match course {
+            JsonCourseVersion::V1(mut course) => {
+                course.sort();
+                if let Some(seed) = &options.exam_seed {
+                    course.shuffle(seed);
+                }
+
+                let progress = match options.record {
+                    Some(_) => progress.recorded(),
+                    None => progress,
                 };
-                log::error!("{msg}");
 
-                RunnerVersion::Undefined
+                let background = Background::new(options.kill_grace);
+                let runner = TestRunnerV1::new(
+                    progress,
+                    vec![],
+                    TestRunnerState::Loaded,
+                    course,
+                    options,
+                    Instant::now(),
+                    BTreeMap::new(),
+                    background,
+                    false,
+                );
+
+                RunnerVersion::V1(runner)
             }
         }
     }
 }
 
+/// Displays why a course could not be loaded, on `stderr`.
+///
+/// * `err`: error which occurred while loading the course.
+/// * `summary`: message explaining the consequences of the error.
+/// * `options`: settings affecting how the error is displayed.
+pub fn report_load_error(
+    err: &ParsingError,
+    summary: &str,
+    options: &RunnerOptions,
+) {
+    eprintln!(
+        "\n{} {}: {}\n    {err}",
+        options.glyphs().warning,
+        options.locale.catalog().error,
+        (options.theme().error)(summary)
+    );
+}
+
 impl Runner for RunnerVersion {
     fn run(self) -> Self {
-        match self {
+        let state = self.state();
+        let next = match self {
             RunnerVersion::V1(runner) => Self::V1(runner.run()),
             RunnerVersion::Undefined => Self::Undefined,
-        }
+        };
+
+        debug_assert!(
+            state.can_transition(&next.state()),
+            "unexpected transition from {} to {}",
+            state.name(),
+            next.state().name()
+        );
+
+        next
     }
 
     fn state(&self) -> TestRunnerState {
@@ -120,6 +884,353 @@ impl Runner for RunnerVersion {
             RunnerVersion::Undefined => TestRunnerState::Finish,
         }
     }
+
+    fn results(&self) -> &[TestOutcome] {
+        match self {
+            RunnerVersion::V1(runner) => runner.results(),
+            RunnerVersion::Undefined => &[],
+        }
+    }
+}
+
+/// Runs a course to completion, yielding the result of each test as soon as
+/// it has run.
+impl Iterator for RunnerVersion {
+    type Item = TestOutcome;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let count = self.results().len();
+
+        while self.state() != TestRunnerState::Finish {
+            let runner = std::mem::replace(self, RunnerVersion::Undefined);
+            *self = runner.run();
+
+            if let Some(outcome) = self.results().get(count) {
+                return Some(outcome.clone());
+            }
+        }
+
+        None
+    }
+}
+
+impl RunnerVersion {
+    /// Runs a course to completion, returning the result of every test run.
+    pub fn run_all(self) -> Vec<TestOutcome> {
+        self.collect()
+    }
+}
+
+impl<'a> Summary<'a> {
+    /// Summarizes the results of a run.
+    ///
+    /// The run is considered failed if any mandatory test failed. Optional
+    /// tests count towards the score like mandatory ones, while skipped tests
+    /// are excluded from the total when computing the score.
+    ///
+    /// * `course`: name of the course.
+    /// * `total`: number of tests in the course, including those not run.
+    /// * `results`: results of the tests run so far.
+    pub fn new(
+        course: &'a str,
+        total: usize,
+        results: &'a [TestOutcome],
+    ) -> Self {
+        let count =
+            |status| results.iter().filter(|r| r.status == status).count();
+        let passed = count(TestStatus::Passed);
+        let skipped = count(TestStatus::Skipped);
+
+        let failed_test = results
+            .iter()
+            .find(|r| r.mandatory && r.status == TestStatus::Failed)
+            .map(|r| r.test.as_str());
+
+        Self {
+            course,
+            result: match failed_test {
+                Some(_) => TestStatus::Failed,
+                None => TestStatus::Passed,
+            },
+            // Runs where no test counts towards the score, such as courses
+            // without any test, score 0 rather than NaN
+            score: match total.saturating_sub(skipped) {
+                0 => 0.0,
+                counted => passed as f64 / counted as f64 * 100f64,
+            },
+            passed,
+            failed: count(TestStatus::Failed),
+            skipped,
+            total,
+            failed_test,
+            bonus: None,
+            tests: results,
+        }
+    }
+
+    /// Leaves optional tests out of the score entirely, as some grading
+    /// policies require, the optional tests which passed being counted as a
+    /// separate bonus instead. Counts of passed, failed and skipped tests then
+    /// only cover mandatory tests.
+    ///
+    /// * `total`: number of mandatory tests in the course, including those not
+    ///   run.
+    pub fn without_optional(self, total: usize) -> Self {
+        let count = |mandatory, status| {
+            self.tests
+                .iter()
+                .filter(|r| r.mandatory == mandatory && r.status == status)
+                .count()
+        };
+        let passed = count(true, TestStatus::Passed);
+        let skipped = count(true, TestStatus::Skipped);
+
+        Self {
+            score: match total.saturating_sub(skipped) {
+                0 => 0.0,
+                counted => passed as f64 / counted as f64 * 100f64,
+            },
+            passed,
+            failed: count(true, TestStatus::Failed),
+            skipped,
+            total,
+            bonus: Some(count(false, TestStatus::Passed)),
+            ..self
+        }
+    }
+}
+
+impl Summary<'_> {
+    /// Renders the summary as a Markdown report, for sharing results in pull
+    /// requests.
+    ///
+    /// The report holds a table of every test which was run, followed by the
+    /// output of each failed test in a collapsible block.
+    ///
+    /// * `msg`: messages in the language of the report.
+    /// * `glyphs`: markers to decorate the result of tests with.
+    pub fn to_markdown(&self, msg: &Catalog, glyphs: &Glyphs) -> String {
+        let escape = |cell: &str| cell.replace('|', "\\|");
+        let mut report = format!(
+            "# {}: {} ({:.2}%)\n\n\
+             | # | Suite | Test | Result | Duration |\n\
+             | --- | --- | --- | --- | --- |\n",
+            self.course,
+            self.result.label(msg, glyphs),
+            self.score
+        );
+
+        for test in self.tests {
+            let status = match test.skip_reason {
+                Some(reason) => {
+                    format!(
+                        "{} ({})",
+                        test.status.label(msg, glyphs),
+                        reason.name()
+                    )
+                }
+                None => test.status.label(msg, glyphs),
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {status} | {} ms |\n",
+                test.id,
+                escape(&test.suite),
+                escape(&test.test),
+                test.duration.as_millis()
+            ));
+        }
+
+        let failures =
+            self.tests.iter().filter(|t| t.status == TestStatus::Failed);
+        for (index, test) in failures.enumerate() {
+            if index == 0 {
+                report.push_str("\n## Failures\n");
+            }
+            report.push_str(&format!(
+                "\n<details>\n<summary>{} {} / {}</summary>\n\n\
+                 ````\n{}\n````\n\n</details>\n",
+                test.id,
+                test.suite,
+                test.test,
+                test.output.trim_end()
+            ));
+        }
+
+        report
+    }
+
+    /// Renders the score as a [shields.io endpoint] JSON file, which students
+    /// can embed in the README of their repository as a badge.
+    ///
+    /// [shields.io endpoint]: https://shields.io/badges/endpoint-badge
+    pub fn to_badge(&self) -> String {
+        let badge = serde_json::json!({
+            "schemaVersion": 1,
+            "label": self.course,
+            "message": format!("{:.0}%", self.score),
+            "color": self.badge_color(),
+        });
+        serde_json::to_string_pretty(&badge).unwrap() + "\n"
+    }
+
+    /// Renders the score as a standalone SVG badge, for repositories which do
+    /// not want to depend on shields.io, see [Summary::to_badge].
+    ///
+    /// Text widths are estimated from the number of characters, which is
+    /// close enough for the short labels of a badge.
+    pub fn to_badge_svg(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let (label, message) =
+            (escape(self.course), format!("{:.0}%", self.score));
+        let width = |text: &str| text.chars().count() * 7 + 10;
+        let (left, right) = (width(&label), width(&message));
+        let color = match self.badge_color() {
+            "brightgreen" => "#4c1",
+            "yellow" => "#dfb317",
+            _ => "#e05d44",
+        };
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{total}\" \
+             height=\"20\" role=\"img\" aria-label=\"{label}: {message}\">\n\
+             <rect width=\"{left}\" height=\"20\" fill=\"#555\"/>\n\
+             <rect x=\"{left}\" width=\"{right}\" height=\"20\" \
+             fill=\"{color}\"/>\n\
+             <g fill=\"#fff\" text-anchor=\"middle\" \
+             font-family=\"Verdana,sans-serif\" font-size=\"11\">\n\
+             <text x=\"{label_x}\" y=\"14\">{label}</text>\n\
+             <text x=\"{message_x}\" y=\"14\">{message}</text>\n\
+             </g>\n\
+             </svg>\n",
+            total = left + right,
+            label_x = left / 2,
+            message_x = left + right / 2,
+        )
+    }
+
+    /// Color of the badge of a run, from green for high scores to red.
+    fn badge_color(&self) -> &'static str {
+        match self.score {
+            score if score >= 90.0 => "brightgreen",
+            score if score >= 60.0 => "yellow",
+            _ => "red",
+        }
+    }
+
+    /// Renders the summary as a single line of `key=value` pairs, such as
+    /// `RESULT=PASS score=87.50 passed=42 failed=3 skipped=0 total=50`.
+    ///
+    /// The format of this line is stable so that CI can reliably find it in
+    /// logs.
+    pub fn to_result_line(&self) -> String {
+        let line = format!(
+            "RESULT={} score={:.2} passed={} failed={} skipped={} total={}",
+            match self.result {
+                TestStatus::Failed => "FAIL",
+                _ => "PASS",
+            },
+            self.score,
+            self.passed,
+            self.failed,
+            self.skipped,
+            self.total
+        );
+
+        // Only added when optional tests are left out of the score, so that
+        // the line is unchanged otherwise
+        match self.bonus {
+            Some(bonus) => format!("{line} bonus={bonus}"),
+            None => line,
+        }
+    }
+}
+
+impl TestStatus {
+    /// Human-readable label of a status, used in reports.
+    ///
+    /// * `msg`: messages in the language of the report.
+    /// * `glyphs`: markers to decorate the label with.
+    fn label(&self, msg: &Catalog, glyphs: &Glyphs) -> String {
+        let (glyph, name) = match self {
+            TestStatus::Passed => (glyphs.pass, msg.passed),
+            TestStatus::Failed => (glyphs.fail, msg.failed),
+            TestStatus::Skipped => (glyphs.skip, msg.skipped),
+        };
+        format!("{glyph} {name}")
+    }
+}
+
+/// Saves the [RunState] of a run, prints its result line (see
+/// [Summary::to_result_line]) and writes the reports requested in
+/// [RunnerOptions] once it has finished.
+///
+/// * `summary`: results of the run.
+/// * `options`: settings selecting which reports to write.
+/// * `progress`: where the output of the run was displayed, saved if it was
+///   recorded.
+fn write_reports(
+    summary: &Summary,
+    options: &RunnerOptions,
+    progress: &Console,
+) {
+    // Printed whatever the output mode, on stderr so that it does not mix
+    // with machine-readable output
+    eprintln!("{}", summary.to_result_line());
+
+    if let Some(path) = &options.state_path {
+        RunState::new(summary.course, summary.tests, options.retry.as_ref())
+            .finished()
+            .save(path);
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(summary).unwrap());
+    }
+    if let Some(path) = &options.summary_json_path {
+        let json = serde_json::to_string_pretty(summary).unwrap() + "\n";
+        if let Err(err) = std::fs::write(path, json) {
+            log::error!("failed to write {}: {err}", path.display());
+        }
+    }
+    if options.score_only {
+        println!("{:.2}", summary.score);
+    }
+    if let Some(path) = &options.markdown {
+        if let Err(err) = std::fs::write(
+            path,
+            summary.to_markdown(options.locale.catalog(), options.glyphs()),
+        ) {
+            log::error!("failed to write {}: {err}", path.display());
+        }
+    }
+    if let Some(path) = &options.badge {
+        let badge = match path.extension().is_some_and(|ext| ext == "svg") {
+            true => summary.to_badge_svg(),
+            false => summary.to_badge(),
+        };
+        if let Err(err) = std::fs::write(path, badge) {
+            log::error!("failed to write {}: {err}", path.display());
+        }
+    }
+    if let Some(path) = &options.record {
+        progress.save_recording(path);
+    }
+}
+
+fn is_single(attempts: &u32) -> bool {
+    *attempts <= 1
+}
+
+fn serialize_millis<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
 }
 
 /// Formats tests `stderr` and `stdout` output.
@@ -132,31 +1243,699 @@ impl Runner for RunnerVersion {
 /// ╰─[ {msg} ]
 /// ```
 ///
+/// If the output is empty or only contains whitespace, the box is omitted and
+/// only the message is displayed. Lines longer than `max_line_length` are
+/// truncated so they do not break the layout of the box.
+///
 /// * `output`: test output.
 /// * `msg`: custom message to display after the output.
-fn format_output(output: &str, msg: &str) -> String {
-    let output = output.replace("\n", "\n    │");
-    format!("    ╭─[ output ]{output}\n    ╰─[ {msg} ]")
+/// * `glyphs`: characters used to draw the box.
+/// * `max_line_length`: number of characters after which lines are truncated,
+///   0 for no limit.
+fn format_output(
+    output: &str,
+    msg: &str,
+    glyphs: &Glyphs,
+    max_line_length: usize,
+) -> String {
+    if output.trim().is_empty() {
+        return format!("    {msg}");
+    }
+
+    let output = output
+        .split('\n')
+        .map(|line| truncate_line(line, max_line_length))
+        .collect::<Vec<_>>()
+        .join(&format!("\n    {}", glyphs.box_side));
+    format!("{}{output}\n{}", format_header(glyphs), format_footer(msg, glyphs))
+}
+
+/// Cuts a line of output after `max` characters, ending it with an ellipsis.
+///
+/// * `line`: line to truncate.
+/// * `max`: number of characters to keep, 0 for no limit.
+fn truncate_line(line: &str, max: usize) -> Cow<'_, str> {
+    match line.char_indices().nth(max) {
+        Some((index, _)) if max > 0 => format!("{}…", &line[..index]).into(),
+        _ => line.into(),
+    }
+}
+
+/// Formats a test on a single line, such as `✅ suite/test 0.3s`, see
+/// [RunnerOptions::compact].
+///
+/// * `status`: status the test finished with.
+/// * `name`: suite and name of the test.
+/// * `detail`: why the test was skipped or kept, if it was.
+/// * `duration`: time taken by the test, displayed only if it passed.
+/// * `glyphs`: marker displayed before the test.
+/// * `theme`: styles used for the line.
+fn format_compact(
+    status: TestStatus,
+    name: &str,
+    detail: Option<&str>,
+    duration: Duration,
+    glyphs: &Glyphs,
+    theme: &Theme,
+) -> String {
+    let detail =
+        detail.map(|detail| format!(" ({detail})")).unwrap_or_default();
+
+    match status {
+        TestStatus::Passed => format!(
+            "  {} {name} {}{detail}",
+            glyphs.pass,
+            (theme.muted)(&format!("{:.1}s", duration.as_secs_f64()))
+        ),
+        TestStatus::Failed => {
+            (theme.fail)(&format!("  {} {name}{detail}", glyphs.fail))
+                .to_string()
+        }
+        TestStatus::Skipped => {
+            (theme.skip)(&format!("  {} {name}{detail}", glyphs.skip))
+                .to_string()
+        }
+    }
+}
+
+/// Formats the top of the box around test output, see [format_output].
+///
+/// * `glyphs`: characters used to draw the box.
+fn format_header(glyphs: &Glyphs) -> String {
+    format!("    {}[ output ]", glyphs.box_top)
+}
+
+/// Formats the bottom of the box around test output, see [format_output].
+///
+/// * `msg`: custom message to display after the output.
+/// * `glyphs`: characters used to draw the box.
+fn format_footer(msg: &str, glyphs: &Glyphs) -> String {
+    format!("    {}[ {msg} ]", glyphs.box_bottom)
 }
 
-fn format_spinner(progress: &ProgressBar) {
+/// Formats the total time taken by a run.
+///
+/// * `started`: when the run started.
+/// * `msg`: messages used to label the time.
+/// * `glyphs`: marker displayed before the time.
+fn format_total_time(
+    started: Instant,
+    msg: &Catalog,
+    glyphs: &Glyphs,
+) -> String {
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let time = match elapsed < 60.0 {
+        true => format!("{elapsed:.1}s"),
+        false => {
+            format!("{}m {:02}s", elapsed as u64 / 60, elapsed as u64 % 60)
+        }
+    };
+
+    format!("{} {}: {time}", glyphs.time, msg.total_time)
+}
+
+/// Formats how the score of a run was computed: how each test contributed to
+/// it, followed by the arithmetic producing the final percentage.
+///
+/// Every test counts for one point if it passed and none otherwise, whether it
+/// is optional or not, unless optional tests are left out of the score (see
+/// [Summary::without_optional]). Skipped tests are left out of the total,
+/// while tests which were not run because the run stopped early count as not
+/// passed.
+///
+/// * `summary`: results of the run.
+/// * `msg`: messages used to label the breakdown.
+/// * `glyphs`: marker displayed before the breakdown.
+/// * `theme`: styles used for the breakdown.
+fn format_scoring(
+    summary: &Summary,
+    msg: &Catalog,
+    glyphs: &Glyphs,
+    theme: &Theme,
+) -> String {
+    // Optional tests may be left out of the score, only counting as a bonus
+    let counted = |r: &TestOutcome| summary.bonus.is_none() || r.mandatory;
+    let mut lines = summary
+        .tests
+        .iter()
+        .map(|r| {
+            let (status, points) = match (r.status, counted(r)) {
+                (TestStatus::Passed, true) => ((theme.pass)(msg.passed), "+1"),
+                (TestStatus::Passed, false) => {
+                    ((theme.pass)(msg.passed), msg.bonus_point)
+                }
+                (TestStatus::Failed, true) => ((theme.fail)(msg.failed), "+0"),
+                (TestStatus::Failed, false) => {
+                    ((theme.fail)(msg.failed), msg.not_counted)
+                }
+                (TestStatus::Skipped, _) => {
+                    ((theme.skip)(msg.skipped), msg.not_counted)
+                }
+            };
+            let optional = match r.mandatory {
+                true => String::new(),
+                false => format!(" {}", msg.optional),
+            };
+
+            format!(
+                "    {} {} / {}{optional}: {status}, {points}",
+                r.id, r.suite, r.test
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let ran = summary.tests.iter().filter(|r| counted(r)).count();
+    let not_run = summary.total.saturating_sub(ran);
+    if not_run > 0 {
+        lines.push(format!(
+            "    {}: +0",
+            Catalog::format(msg.not_run, "count", &not_run.to_string())
+        ));
+    }
+
+    let formula = [
+        ("passed", summary.passed),
+        ("counted", summary.total.saturating_sub(summary.skipped)),
+        ("total", summary.total),
+        ("skipped", summary.skipped),
+    ]
+    .into_iter()
+    .fold(msg.scoring_formula.to_string(), |formula, (key, value)| {
+        Catalog::format(&formula, key, &value.to_string())
+    });
+    let formula =
+        Catalog::format(&formula, "score", &format!("{:.2}", summary.score));
+
+    format!(
+        "\n{} {}:\n{}\n    {}",
+        glyphs.finish,
+        msg.scoring,
+        lines.join("\n"),
+        (theme.emphasis)(&formula)
+    )
+}
+
+/// Formats the slowest tests of a run, slowest first, like pytest's
+/// `--durations`. Returns [None] if no test took any time, such as when every
+/// test was kept from the previous run.
+///
+/// * `results`: results of the tests run.
+/// * `count`: maximum number of tests to list.
+/// * `msg`: messages used to label the list.
+/// * `glyphs`: marker displayed before the list.
+/// * `theme`: styles used for the list.
+fn format_slowest(
+    results: &[TestOutcome],
+    count: usize,
+    msg: &Catalog,
+    glyphs: &Glyphs,
+    theme: &Theme,
+) -> Option<String> {
+    let mut slowest =
+        results.iter().filter(|r| !r.duration.is_zero()).collect::<Vec<_>>();
+    slowest.sort_by_key(|r| std::cmp::Reverse(r.duration));
+
+    let lines = slowest
+        .into_iter()
+        .take(count)
+        .map(|r| {
+            format!(
+                "    {:>8}ms  {} {} / {}",
+                r.duration.as_millis(),
+                r.id,
+                r.suite,
+                r.test
+            )
+        })
+        .collect::<Vec<_>>();
+
+    (!lines.is_empty()).then(|| {
+        format!(
+            "\n{} {}:\n{}",
+            glyphs.time,
+            msg.slowest_tests,
+            (theme.muted)(&lines.join("\n"))
+        )
+    })
+}
+
+/// Formats how many tests of each group passed, such as `crypto: 8/10`. Groups
+/// are listed in the order in which they first appear and skipped tests are
+/// left out. Returns [None] if there are no groups.
+///
+/// Tests with several tags count towards each of them, while tests without
+/// any tag are left out when grouping by tag.
+///
+/// * `results`: results of the tests run.
+/// * `group_by`: how to group the tests.
+/// * `msg`: messages used to label the groups.
+/// * `glyphs`: marker displayed before the groups.
+/// * `theme`: styles used for the groups.
+fn format_groups(
+    results: &[TestOutcome],
+    group_by: GroupBy,
+    msg: &Catalog,
+    glyphs: &Glyphs,
+    theme: &Theme,
+) -> Option<String> {
+    let mut groups: Vec<(&str, usize, usize)> = Vec::new();
+
+    for result in results.iter().filter(|r| r.status != TestStatus::Skipped) {
+        let names = match group_by {
+            GroupBy::Suite => vec![result.suite.as_str()],
+            GroupBy::Tag => result.tags.iter().map(String::as_str).collect(),
+        };
+
+        for name in names {
+            let index = match groups.iter().position(|(n, ..)| *n == name) {
+                Some(index) => index,
+                None => {
+                    groups.push((name, 0, 0));
+                    groups.len() - 1
+                }
+            };
+            let (_, passed, total) = &mut groups[index];
+            *passed += usize::from(result.status == TestStatus::Passed);
+            *total += 1;
+        }
+    }
+
+    let lines = groups
+        .into_iter()
+        .map(|(name, passed, total)| format!("    {name}: {passed}/{total}"))
+        .collect::<Vec<_>>();
+    let title = match group_by {
+        GroupBy::Suite => msg.results_by_suite,
+        GroupBy::Tag => msg.results_by_tag,
+    };
+
+    (!lines.is_empty()).then(|| {
+        format!(
+            "\n{} {title}:\n{}",
+            glyphs.exercises,
+            (theme.muted)(&lines.join("\n"))
+        )
+    })
+}
+
+fn format_spinner(progress: &ProgressBar, glyphs: &Glyphs) {
     progress.set_style(
         ProgressStyle::with_template("\n{spinner} {msg} {elapsed_precise}")
-            .unwrap(),
+            .unwrap()
+            .tick_chars(glyphs.spinner),
     );
     progress.enable_steady_tick(Duration::from_millis(50));
 }
 
-fn format_bar(progress: &ProgressBar) {
+/// Displays the position of the progress bar in the title of the terminal, such
+/// as `dotcodeschool 12/50`, so that it remains visible when the window is in
+/// the background.
+///
+/// Nothing is written when the progress bar is hidden or `stderr` is not a
+/// terminal which supports titles.
+///
+/// * `progress`: progress bar of the run.
+fn set_title(progress: &ProgressBar) {
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    if progress.is_hidden() || dumb || !std::io::stderr().is_terminal() {
+        return;
+    }
+
+    let total = progress.length().unwrap_or_default();
+    eprint!("\x1b]0;dotcodeschool {}/{total}\x07", progress.position());
+}
+
+fn format_bar(progress: &ProgressBar, glyphs: &Glyphs) {
+    progress.disable_steady_tick();
     progress.set_style(
-        ProgressStyle::with_template("{wide_bar} {message} {elapsed_precise}")
-            .unwrap(),
+        ProgressStyle::with_template("{wide_bar} {msg} {elapsed_precise}")
+            .unwrap()
+            .progress_chars(glyphs.bar),
     );
 }
 
+/// Displays the buffered output of tests grouped into passed, failed and
+/// skipped sections, see [RunnerOptions::buffered].
+///
+/// * `progress`: progress bar to print above.
+/// * `results`: results of the tests run so far.
+/// * `msg`: messages used for section titles.
+/// * `theme`: styles used for section titles.
+fn print_grouped(
+    progress: &Console,
+    results: &[TestOutcome],
+    msg: &Catalog,
+    theme: &Theme,
+) {
+    let sections = [
+        (TestStatus::Passed, msg.passed),
+        (TestStatus::Failed, msg.failed),
+        (TestStatus::Skipped, msg.skipped),
+    ];
+
+    for (status, title) in sections {
+        let tests = results.iter().filter(|r| r.status == status);
+        for (index, test) in tests.enumerate() {
+            if index == 0 {
+                progress.println(format!(
+                    "\n{}",
+                    (theme.emphasis)(&title.to_uppercase())
+                ));
+            }
+            progress.println(&test.rendered);
+        }
+    }
+}
+
+/// Runs a test on a background thread, switching the progress bar to a spinner
+/// if it takes longer than [SPINNER_DELAY] so students know it is still alive.
+///
+/// Fast tests are left untouched and do not cause any change in output. A test
+/// which panics fails instead of bringing down the whole run.
+///
+/// * `progress`: progress bar, restored once the test has finished.
+/// * `test`: test to run.
+/// * `msg`: styled message to display next to the spinner.
+/// * `glyphs`: characters used to draw the spinner and progress bar.
+/// * `ctx`: settings affecting how the test is executed.
+fn run_with_spinner<T: Test + Sync>(
+    progress: &ProgressBar,
+    test: &T,
+    msg: &str,
+    glyphs: &Glyphs,
+    ctx: &ExecContext,
+) -> TestResult {
+    thread::scope(|s| {
+        let handle = s.spawn(|| test.run(ctx));
+        let start = Instant::now();
+        let mut spinning = false;
+
+        while !handle.is_finished() {
+            if !spinning && start.elapsed() > SPINNER_DELAY {
+                format_spinner(progress, glyphs);
+                progress.set_message(msg.to_string());
+                spinning = true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        if spinning {
+            progress.set_message("");
+            format_bar(progress, glyphs);
+        }
+
+        handle.join().unwrap_or_else(panic_result)
+    })
+}
+
+/// Runs a single test outside of any course, returning its result and how long
+/// it took.
+///
+/// The test is skipped if its guard says so. A test which panics fails instead
+/// of bringing down the caller. Nothing is displayed, making this suitable for
+/// embedding the execution of tests in other tools.
+///
+/// * `test`: test to run.
+/// * `ctx`: settings affecting how the test is executed.
+pub fn run_test<T: Test>(
+    test: &T,
+    ctx: &ExecContext,
+) -> (TestResult, Duration) {
+    let start = Instant::now();
+    let result = match test.skip(ctx) {
+        Some(reason) => TestResult::Skip(SkipReason::Guard, reason),
+        None => panic::catch_unwind(AssertUnwindSafe(|| test.run(ctx)))
+            .unwrap_or_else(panic_result),
+    };
+
+    (result, start.elapsed())
+}
+
+/// Result of a test run ahead of its turn, when running tests in parallel.
+///
+/// * `result`: whether the test passed, failed or was skipped.
+/// * `duration`: time taken to run the test.
+/// * `usage`: resources used by the commands of the test.
+/// * `timed_out`: whether a command of the test exceeded its timeout.
+/// * `attempts`: number of times the test was run.
+struct TestRun {
+    result: TestResult,
+    duration: Duration,
+    usage: Option<ResourceUsage>,
+    timed_out: bool,
+    attempts: u32,
+}
+
+/// Bounds the total output buffered by tests running at the same time, see
+/// [RunnerOptions::max_concurrent_output].
+///
+/// * `limit`: number of bytes above which no new test is started.
+/// * `usage`: output buffered by the tests currently running, and how many
+///   there are.
+/// * `released`: notified whenever a test finishes, freeing its output.
+struct OutputBudget {
+    limit: usize,
+    usage: Mutex<BudgetUsage>,
+    released: Condvar,
+}
+
+/// Output buffered by the tests currently running, see [OutputBudget].
+///
+/// * `used`: bytes of output buffered by the tests currently running.
+/// * `running`: number of tests currently running.
+#[derive(Default)]
+struct BudgetUsage {
+    used: usize,
+    running: usize,
+}
+
+impl OutputBudget {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            usage: Mutex::new(BudgetUsage::default()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until the output buffered by running tests is back under the
+    /// limit before a new test starts. A test always starts if none is
+    /// running, so that a single chatty test cannot stall the run.
+    ///
+    /// The check and the start of the test happen under the same lock, so
+    /// that tests waiting at the same time cannot all start at once.
+    fn acquire(&self) {
+        let over = |usage: &mut BudgetUsage| {
+            usage.running > 0 && usage.used > self.limit
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        if over(&mut usage) {
+            log::debug!(
+                "Over {} bytes of output buffered, waiting for running tests \
+                 to finish",
+                self.limit
+            );
+        }
+        let mut usage = self.released.wait_while(usage, over).unwrap();
+        usage.running += 1;
+    }
+
+    /// Accounts for output captured by a running test.
+    ///
+    /// * `bytes`: number of bytes captured.
+    fn charge(&self, bytes: usize) {
+        self.usage.lock().unwrap().used += bytes;
+    }
+
+    /// Frees the output buffered by a test once it has finished.
+    ///
+    /// * `bytes`: number of bytes the test was charged for.
+    fn release(&self, bytes: usize) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.used -= bytes;
+        usage.running -= 1;
+        self.released.notify_all();
+    }
+}
+
+/// Runs several tests on background threads, at most `jobs` at a time, and
+/// returns their results in the same order as `tests`.
+///
+/// Tests whose guard says they should be skipped are skipped. Like
+/// [run_with_spinner], a test which panics fails instead of bringing down the
+/// whole run.
+///
+/// * `tests`: tests to run.
+/// * `jobs`: maximum number of tests running at the same time.
+/// * `budget`: bounds the output buffered by running tests, shared with any
+///   other tests running at the same time. Unbounded if [None].
+/// * `ctx`: settings affecting how the tests are executed.
+fn run_parallel<T: Test + Sync>(
+    tests: &[&T],
+    jobs: usize,
+    budget: Option<&OutputBudget>,
+    ctx: &ExecContext,
+) -> Vec<TestRun> {
+    let next = AtomicUsize::new(0);
+    let runs = Mutex::new((0..tests.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, tests.len().max(1)) {
+            s.spawn(|| {
+                // Threads pick the next test left to run until there is none
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(test) = tests.get(index) else { break };
+
+                    if let Some(budget) = budget {
+                        budget.acquire();
+                    }
+                    let buffered = AtomicUsize::new(0);
+                    let on_output = |bytes| {
+                        buffered.fetch_add(bytes, Ordering::Relaxed);
+                        if let Some(budget) = budget {
+                            budget.charge(bytes);
+                        }
+                    };
+                    let usage = Mutex::new(None);
+                    let on_usage = |used| record_usage(&usage, used);
+                    let timed_out = AtomicBool::new(false);
+                    let on_timeout =
+                        || timed_out.store(true, Ordering::Relaxed);
+                    let attempts = AtomicU32::new(1);
+                    let on_retry = || {
+                        attempts.fetch_add(1, Ordering::Relaxed);
+                    };
+                    let ctx = ExecContext {
+                        on_usage: Some(&on_usage),
+                        on_timeout: Some(&on_timeout),
+                        on_retry: Some(&on_retry),
+                        on_output: Some(&on_output),
+                        ..*ctx
+                    };
+
+                    let (result, duration) = run_test(*test, &ctx);
+                    if let Some(budget) = budget {
+                        budget.release(buffered.into_inner());
+                    }
+                    let run = TestRun {
+                        result,
+                        duration,
+                        usage: usage.into_inner().unwrap(),
+                        timed_out: timed_out.into_inner(),
+                        attempts: attempts.into_inner(),
+                    };
+
+                    runs.lock().unwrap()[index] = Some(run);
+                }
+            });
+        }
+    });
+
+    runs.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+/// Adds the resources used by a command to those used by the previous
+/// commands of a test.
+///
+/// * `usage`: resources used by the test so far.
+/// * `used`: resources used by the command.
+fn record_usage(usage: &Mutex<Option<ResourceUsage>>, used: ResourceUsage) {
+    let mut usage = usage.lock().unwrap();
+    *usage = Some(usage.map_or(used, |usage| usage.merge(used)));
+}
+
+/// Converts the payload of a panic which occurred while running a test into
+/// a failure.
+///
+/// * `panic`: payload of the panic.
+fn panic_result(panic: Box<dyn Any + Send>) -> TestResult {
+    let reason = panic
+        .downcast_ref::<&str>()
+        .map(|reason| reason.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+
+    log::error!("Internal error while running test: {reason}");
+    TestResult::Fail(format!("internal error: {reason}"))
+}
+
+/// Problems with the git repository tests are being run in.
+enum RepoProblem {
+    GitMissing,
+    NotARepo,
+    WrongBranch { expected: String, found: String },
+}
+
+/// Checks that tests are being run inside of a git repository, and on the
+/// expected branch if one is specified.
+///
+/// * `branch`: branch tests are expected to be run on, if any.
+fn check_repo(branch: Option<&str>) -> Option<RepoProblem> {
+    let git =
+        |args: &[&str]| std::process::Command::new("git").args(args).output();
+
+    // Unlike resolving `HEAD`, this also works in a repository without any
+    // commit yet, as when a student has just cloned an empty template
+    let output = match git(&["rev-parse", "--is-inside-work-tree"]) {
+        Ok(output) => output,
+        Err(_) => return Some(RepoProblem::GitMissing),
+    };
+
+    if !output.status.success() || output.stdout.trim_ascii() != b"true" {
+        return Some(RepoProblem::NotARepo);
+    }
+
+    let expected = branch?;
+    // A detached `HEAD` is not on any branch
+    let found = git(&["symbolic-ref", "--short", "HEAD"])
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        })
+        .unwrap_or_else(|| "HEAD".to_string());
+
+    (expected != found).then(|| RepoProblem::WrongBranch {
+        expected: expected.to_string(),
+        found,
+    })
+}
+
+/// Checks that the installed Rust toolchain is at least `min`, returning a
+/// description of the problem if it is not or cannot be determined.
+///
+/// * `min`: oldest supported version, such as `1.80`.
+/// * `msg`: messages to describe the problem with.
+fn check_toolchain(min: &str, msg: &Catalog) -> Option<String> {
+    let output = std::process::Command::new("rustc").arg("--version").output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => return Some(msg.rustc_missing.to_string()),
+    };
+
+    // Versions are displayed as `rustc 1.80.1 (3f5fd8dd4 2024-08-06)`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let found = stdout.split_whitespace().nth(1).unwrap_or_default();
+    let found = found.split('-').next().unwrap_or_default();
+
+    match (parse_version(found), parse_version(min)) {
+        (Some(version), Some(expected)) if version < expected => {
+            Some(Catalog::format(
+                &Catalog::format(msg.rustc_too_old, "expected", min),
+                "found",
+                found,
+            ))
+        }
+        _ => None,
+    }
+}
+
 fn submodule_name(stdout: &str) -> String {
     let regex = Regex::new(r"-[abcdef0123456789]* (?<submodule>\w*)").unwrap();
-    let capture = regex.captures(&stdout).map(|c| c["submodule"].to_string());
+    let capture = regex.captures(stdout).map(|c| c["submodule"].to_string());
 
     // extracts the submodule name
     match capture {
@@ -164,3 +1943,74 @@ fn submodule_name(stdout: &str) -> String {
         None => "".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct PanickingTest;
+
+    impl Test for PanickingTest {
+        fn run(&self, _ctx: &ExecContext) -> TestResult {
+            panic!("boom")
+        }
+    }
+
+    struct PassingTest;
+
+    impl Test for PassingTest {
+        fn run(&self, _ctx: &ExecContext) -> TestResult {
+            TestResult::Pass("ok".to_string())
+        }
+    }
+
+    #[test]
+    fn run_test_reports_panic_as_failure() {
+        let ctx = ExecContext::default();
+
+        let (result, _) = run_test(&PanickingTest, &ctx);
+        match result {
+            TestResult::Fail(reason) => {
+                assert_eq!(reason, "internal error: boom")
+            }
+            _ => panic!("a panicking test should fail"),
+        }
+
+        // The panic is contained, so the tests after it still run
+        let (result, _) = run_test(&PassingTest, &ctx);
+        assert!(matches!(result, TestResult::Pass(_)));
+    }
+
+    #[test]
+    fn output_budget_waits_for_release() {
+        let budget = OutputBudget::new(10);
+        let started = AtomicBool::new(false);
+
+        budget.acquire();
+        budget.charge(20);
+        thread::scope(|s| {
+            s.spawn(|| {
+                budget.acquire();
+                started.store(true, Ordering::Release);
+            });
+
+            // Over the limit with a test running, so the next one waits
+            thread::sleep(Duration::from_millis(50));
+            assert!(!started.load(Ordering::Acquire));
+            budget.release(20);
+        });
+
+        assert!(started.into_inner());
+        assert_eq!(budget.usage.lock().unwrap().running, 1);
+    }
+
+    #[test]
+    fn run_with_spinner_reports_panic_as_failure() {
+        let progress = ProgressBar::hidden();
+        let ctx = ExecContext::default();
+
+        let result =
+            run_with_spinner(&progress, &PanickingTest, "", &ASCII, &ctx);
+        assert!(matches!(result, TestResult::Fail(_)));
+    }
+}
@@ -0,0 +1,82 @@
+//! A module for recording the output of a run and replaying it later.
+//!
+//! Recordings hold every line displayed during a run along with when it was
+//! displayed, so that a run can be replayed with realistic timing without
+//! executing any command, for documentation and demos.
+
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A line of output, displayed `at_ms` milliseconds after the start of the
+/// run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedLine {
+    pub at_ms: u64,
+    pub line: String,
+}
+
+/// Output of a run being recorded.
+///
+/// * `started`: when recording started.
+/// * `lines`: every line displayed so far, in order.
+pub struct Recording {
+    started: Instant,
+    lines: Vec<RecordedLine>,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self { started: Instant::now(), lines: Vec::new() }
+    }
+}
+
+impl Recording {
+    /// Records a line of output, timestamped with the time elapsed since
+    /// recording started.
+    ///
+    /// * `line`: line which was displayed.
+    pub fn record(&mut self, line: &str) {
+        self.lines.push(RecordedLine {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            line: line.to_string(),
+        });
+    }
+
+    /// Saves the recording as JSON.
+    ///
+    /// * `path`: file to save the recording to.
+    pub fn save(&self, path: &Path) {
+        let json = serde_json::to_string_pretty(&self.lines)
+            .expect("recording should always serialize");
+
+        if let Err(err) = std::fs::write(path, json + "\n") {
+            log::error!("failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+/// Displays a recorded run again, waiting between lines as long as was waited
+/// when it was recorded. No command is executed.
+///
+/// * `path`: file the run was recorded to.
+pub fn replay(path: &Path) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    let lines = serde_json::from_str::<Vec<RecordedLine>>(&contents).map_err(
+        |err| format!("invalid recording {}: {err}", path.display()),
+    )?;
+
+    let started = Instant::now();
+    for RecordedLine { at_ms, line } in lines {
+        let at = Duration::from_millis(at_ms);
+        thread::sleep(at.saturating_sub(started.elapsed()));
+        println!("{line}");
+    }
+
+    Ok(())
+}
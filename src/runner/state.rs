@@ -0,0 +1,115 @@
+//! A module for persisting the results of a run between invocations.
+//!
+//! The status of every test run is saved to [STATE_FILE] as each test
+//! finishes, so that a later run can focus on the tests which did not pass or
+//! continue a run which was interrupted.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{TestOutcome, TestStatus};
+
+/// File the state of the last run is saved to, in the current directory.
+pub const STATE_FILE: &str = ".dotcodeschool-state.json";
+
+/// Status of every test as of the last run of a course.
+///
+/// * `course`: name of the course.
+/// * `tests`: status of every test which was run.
+/// * `finished`: whether the run went all the way to its end, rather than
+///   being interrupted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RunState {
+    pub course: String,
+    pub tests: Vec<TestRecord>,
+    #[serde(default)]
+    pub finished: bool,
+}
+
+/// Status of a single test, identified by its suite and name so that records
+/// still match if tests are reordered.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TestRecord {
+    pub suite: String,
+    pub test: String,
+    pub status: TestStatus,
+}
+
+impl RunState {
+    /// Builds the state of a run from its results.
+    ///
+    /// Tests which were not reached during the run, such as those after a
+    /// failed mandatory test, keep their status from `previous`.
+    ///
+    /// * `course`: name of the course.
+    /// * `results`: results of the tests run.
+    /// * `previous`: state of the previous run, if it is being updated.
+    pub fn new(
+        course: &str,
+        results: &[TestOutcome],
+        previous: Option<&RunState>,
+    ) -> Self {
+        let mut tests = results
+            .iter()
+            .map(|r| TestRecord {
+                suite: r.suite.clone(),
+                test: r.test.clone(),
+                status: r.status,
+            })
+            .collect::<Vec<_>>();
+
+        let previous = previous.into_iter().flat_map(|state| &state.tests);
+        for record in previous {
+            if !tests.iter().any(|t| t.matches(&record.suite, &record.test)) {
+                tests.push(record.clone());
+            }
+        }
+
+        Self { course: course.to_string(), tests, finished: false }
+    }
+
+    /// Marks the run as having gone all the way to its end.
+    pub fn finished(self) -> Self {
+        Self { finished: true, ..self }
+    }
+
+    /// Loads the state of the last run.
+    ///
+    /// * `path`: path to the state file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            format!("failed to read {}: {err}", path.display())
+        })?;
+
+        serde_json::from_str(&contents).map_err(|err| {
+            format!("invalid state file {}: {err}", path.display())
+        })
+    }
+
+    /// Saves the state of a run, reporting any failure without stopping.
+    ///
+    /// * `path`: path to the state file.
+    pub fn save(&self, path: &Path) {
+        let contents = serde_json::to_string_pretty(self)
+            .expect("run state should always serialize");
+
+        if let Err(err) = std::fs::write(path, contents + "\n") {
+            log::warn!("failed to save run state to {}: {err}", path.display());
+        }
+    }
+
+    /// Returns the status of a test as of the last run, if it was run.
+    ///
+    /// * `suite`: name of the suite of the test.
+    /// * `test`: name of the test.
+    pub fn status(&self, suite: &str, test: &str) -> Option<TestStatus> {
+        self.tests.iter().find(|t| t.matches(suite, test)).map(|t| t.status)
+    }
+}
+
+impl TestRecord {
+    fn matches(&self, suite: &str, test: &str) -> bool {
+        self.suite == suite && self.test == test
+    }
+}
@@ -1,22 +1,41 @@
-use std::{ops::Deref, thread, time::Duration};
-
-use indicatif::ProgressBar;
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::parsing::{v1::JsonCourseV1, Test, TestResult};
+use crate::{
+    locale::Catalog,
+    parsing::{
+        exec::{Background, ExecContext},
+        v1::JsonCourseV1,
+        SkipReason, Test, TestResult,
+    },
+};
 
 use super::{
-    format_bar, format_output, format_spinner, submodule_name, Runner,
-    TestRunnerState, DOTCODESCHOOL, OPTIONAL,
+    check_repo, check_toolchain, fixtures::Fixtures, format_bar,
+    format_compact, format_footer, format_groups, format_header, format_output,
+    format_scoring, format_slowest, format_spinner, format_total_time,
+    print_grouped, record_usage, run_parallel, run_with_spinner, set_title,
+    state::RunState, submodule_name, truncate_line, write_reports, Console,
+    OutputBudget, RepoProblem, Runner, RunnerOptions, Summary, TestOutcome,
+    TestRun, TestRunnerState, TestStatus, DOTCODESCHOOL,
 };
 
-use colored::Colorize;
 use derive_more::Constructor;
 
 pub const TEST_DIR: &str = "./tests";
 
 /// Runs all the tests specified in a `tests.json` file.
 ///
-/// Tests are run sequentially in their order of definition. Running tests
+/// Tests are run sequentially in their order of definition, unless an explicit
+/// `order` is specified (see [JsonCourseV1::sort]). Running tests
 /// occurs in 3 steps:
 ///
 /// 1. Loading the `tests.json` file.
@@ -52,6 +71,24 @@ pub const TEST_DIR: &str = "./tests";
 /// Course Id will be checked against the DotCodeScool servers to make sure that
 /// the tests are being run in the correct git repository.
 ///
+/// A course can also specify the git `branch` tests are expected to be run
+/// on. A warning is displayed if tests are not run inside of a git repository
+/// or on a different branch.
+///
+/// Once every mandatory test has passed, an encouraging message depending on
+/// the final score is displayed. Courses can provide their own messages with
+/// `score_messages`, keyed by the minimum score percentage they apply to:
+///
+/// ```json
+/// {
+///     ...
+///     "score_messages": {
+///         "100": "Flawless!",
+///         "0": "Now try the optional tests"
+///     }
+/// }
+/// ```
+///
 /// ### Suite definition
 ///
 /// ```json
@@ -65,12 +102,31 @@ pub const TEST_DIR: &str = "./tests";
 /// ```
 ///
 /// Test suites marked as optional do not need to be passed for the course to be
-/// validated. They will however still count towards the overall success of the
-/// course, so if a student passes 9 mandatory test suites but fails 1 optional
-/// test suite, their overall score will still be 90%.
+/// validated: every test in an optional suite is optional, whatever its own
+/// `optional` field says. They will however still count towards the overall
+/// success of the course, so if a student passes 9 mandatory test suites but
+/// fails 1 optional test suite, their overall score will still be 90%.
+/// Grading policies which leave optional tests out of the score entirely can
+/// use `--no-optional-in-score`: the score is then computed over mandatory
+/// tests only, optional tests which passed being reported as a bonus.
+///
+/// With `--jobs`, the tests of a suite are run in parallel before being
+/// displayed in order. `--jobs-suite` likewise runs several suites at the same
+/// time, their tests still being displayed in the order of the course.
+///
+/// Suites whose tests share a resource, such as a single test database, can
+/// limit how many of their tests run at the same time with `max_parallel`, `1`
+/// running them one by one:
+///
+/// ```json
+/// {
+///     ...
+///     "max_parallel": 1
+/// }
+/// ```
 ///
 /// ### Test definition
-// ```json
+/// ```json
 /// {
 ///     "name": "Test name",
 ///     "optional": false,
@@ -79,51 +135,588 @@ pub const TEST_DIR: &str = "./tests";
 ///     "message_on_success": "This test passed, congrats!"
 /// }
 /// ```
-/// 
+///
 /// `cmd` defines which command to run for the test to execute. Like test
 /// suites, tests can be marked as `optional`. `optional` tests will still count
 /// towards the overall success of the course but do not need to be validated as
 /// part of a test suite.
 ///
+/// Tests can also have a one-line `description` of what they check, displayed
+/// under the name of the test when it runs.
+///
+/// Suites and tests are run strictly in the order in which they appear in
+/// their array. Both can specify an `order` integer to override this: elements
+/// are sorted by ascending `order`, elements without one being treated as
+/// having an order of 0.
+///
+/// Suites are told apart by name when selecting them and in results, as are
+/// the tests of a suite, so names should be unique within the course and
+/// within each suite respectively. Duplicates are warned about, and prevent
+/// the course from loading with `--strict-json`.
+///
+/// For exams, `--exam-seed` shuffles the tests of each suite in an order
+/// derived from a string such as a student ID: the same student always gets
+/// the same order while different students get different ones. Tests with an
+/// `order` keep their position, as do suites.
+///
+/// Tests which need several steps, such as a build followed by a run, can
+/// specify `cmds` instead of `cmd`:
+///
+/// ```json
+/// {
+///     ...
+///     "cmds": ["cargo build", "cargo run"]
+/// }
+/// ```
+///
+/// Commands are run in order and the test fails at the first command which
+/// does not succeed. Steps can also be given their own `message_on_fail`,
+/// displayed instead of that of the test when they are the step which fails:
+///
+/// ```json
+/// {
+///     ...
+///     "cmds": [
+///         { "cmd": "cargo build", "message_on_fail": "Build failed" },
+///         "cargo test"
+///     ]
+/// }
+/// ```
+///
+/// Commands are split on whitespace and their first word is the binary to
+/// run. Tests can instead specify an `interpreter`, such as `"python3"`, which
+/// is run with each command as its arguments:
+///
+/// ```json
+/// {
+///     ...
+///     "interpreter": "python3",
+///     "cmd": "tests/check_answer.py"
+/// }
+/// ```
+///
+/// Tests can be skipped depending on a precondition with the `skip_if` field:
+///
+/// ```json
+/// {
+///     ...
+///     "skip_if": "test -f .skip"
+/// }
+/// ```
+///
+/// If the `skip_if` command succeeds, the test is skipped and does not count
+/// towards the overall success of the course. The command runs with the same
+/// settings as the commands of tests, such as `--max-output` or `--allow-cmd`,
+/// but without the extra arguments passed after `--`.
+///
+/// Tests can optionally enforce a coverage floor with the `coverage` and
+/// `min_coverage` fields:
+///
+/// ```json
+/// {
+///     ...
+///     "coverage": "cargo tarpaulin",
+///     "min_coverage": 80.0
+/// }
+/// ```
+///
+/// Once `cmd` has succeeded, `coverage` is run and the last percentage in its
+/// output is compared against `min_coverage`. The test fails if coverage is
+/// below that floor.
+///
+/// Tests can be given a `timeout` in seconds:
+///
+/// ```json
+/// {
+///     ...
+///     "timeout": 30
+/// }
+/// ```
+///
+/// Each command of the test is then asked to terminate once it has run for
+/// longer than `timeout`, and killed if it is still running after
+/// `--timeout-kill-grace`. A test which times out fails.
+///
+/// Flaky tests can be retried with `retries`, optionally only when their
+/// output contains `retry_if_output_contains`:
+///
+/// ```json
+/// {
+///     ...
+///     "retries": 2,
+///     "retry_if_output_contains": "connection reset"
+/// }
+/// ```
+///
+/// Failures whose output does not contain `retry_if_output_contains` fail
+/// immediately, so that retries do not mask genuine regressions. Tests which
+/// pass only after being retried are reported as `flaky` in the JSON results,
+/// along with the number of `attempts` they took.
+///
+/// Tests of performance-sensitive exercises can specify how long they are
+/// expected to take with `expected_duration_ms`, optionally allowing them to
+/// take up to `duration_tolerance` percent longer:
+///
+/// ```json
+/// {
+///     ...
+///     "expected_duration_ms": 1000,
+///     "duration_tolerance": 20
+/// }
+/// ```
+///
+/// A warning is displayed if a passing test takes longer than that, and the
+/// test fails instead with `--strict-perf`.
+///
+/// Tests can list the skills they check in `tags`, such as
+/// `["crypto", "networking"]`. With `--group-by tag`, how many tests of each
+/// tag passed is displayed at the end of the run.
+///
+/// Tests added by an update of the course can record the version of the
+/// course they were added in with `added_in`, such as `"1.2"`. Tests added in
+/// the version passed to `--highlight-new` or later are marked as new.
+///
+/// Secrets used by tests, such as API tokens, can be kept out of their output
+/// with `redact`. Values are masked literally, or can name an environment
+/// variable with `env:` or a pattern with `regex:`:
+///
+/// ```json
+/// {
+///     ...
+///     "redact": ["env:API_TOKEN", "regex:sk-[a-z0-9]+"]
+/// }
+/// ```
+///
+/// Suites which only make sense once an earlier suite succeeded can name it in
+/// `requires_suite`:
+///
+/// ```json
+/// {
+///     ...
+///     "requires_suite": "Basics"
+/// }
+/// ```
+///
+/// If any test of that suite failed, the tests of the dependent suite are
+/// skipped. As a failing mandatory test ends the run, this only applies to
+/// prerequisites whose failing tests are optional.
+///
+/// Courses relying on recent language features can specify the oldest Rust
+/// toolchain they support with `min_rust_version`:
+///
+/// ```json
+/// {
+///     ...
+///     "min_rust_version": "1.80"
+/// }
+/// ```
+///
+/// A warning is displayed before any test runs if `rustc` is older than that,
+/// and the run fails instead with `--strict-toolchain`.
+///
+/// Input files needed by tests can be kept out of the student's repository
+/// and provided with `--fixtures`: the contents of the fixture directory are
+/// copied into the current directory before each test runs and removed once it
+/// has finished. Existing files are never overwritten.
+///
+/// Tests are run in the current directory unless they specify a `working_dir`,
+/// resolved against `--root` if it is set. Absolute working directories are
+/// used as-is:
+///
+/// ```json
+/// {
+///     ...
+///     "working_dir": "crates/exercise"
+/// }
+/// ```
+///
+/// Commands and working directories are taken literally unless the test sets
+/// `expand_env`, in which case `$VAR` and `${VAR}` are replaced with the value
+/// of the environment variable, `$$` standing for a literal `$`. Variables
+/// which are not set expand to nothing with a warning, and prevent the course
+/// from loading with `--strict-json`:
+///
+/// ```json
+/// {
+///     ...
+///     "cmd": "$CARGO test",
+///     "expand_env": true
+/// }
+/// ```
+///
+/// Tests can check what their commands print with `expected_stdout`, or with
+/// `expected_stdout_file` for larger outputs, a file relative to the course
+/// file whose contents are the expected output:
+///
+/// ```json
+/// {
+///     ...
+///     "expected_stdout_file": "expected/hello.txt"
+/// }
+/// ```
+///
+/// The test fails if the output of its commands differs, trailing whitespace
+/// being ignored, and displays a diff of the two outputs with
+/// `--diff-context` unchanged lines around each change. Outputs which are not
+/// text, such as images, can be compared byte by byte with
+/// `expected_stdout_file` by setting `binary_stdout`, the offset of the first
+/// difference being reported on mismatch.
+///
+/// Expensive commands can be run once and checked in several ways with
+/// `assertions`, each of which is reported separately:
+///
+/// ```json
+/// {
+///     ...
+///     "cmd": "cargo test",
+///     "assertions": [
+///         { "name": "compiles", "stdout_matches": "running \\d+ tests" },
+///         { "name": "all tests pass", "exit_code": 0 },
+///         { "name": "no warnings", "stderr_empty": true }
+///     ]
+/// }
+/// ```
+///
+/// The test passes if every assertion does. Its command may then exit with
+/// any status, which only matters to assertions checking its `exit_code`.
+///
+/// Tests can start processes which later tests of the suite depend on, such
+/// as a server, by setting `background`. Their commands are started without
+/// being waited for, the test passing if they could be started, and are
+/// stopped along with any process they started once the suite ends, including
+/// when a mandatory test fails or the run is interrupted:
+///
+/// ```json
+/// {
+///     ...
+///     "cmd": "cargo run --bin server",
+///     "background": true
+/// }
+/// ```
+///
+/// Background tests are never run ahead of their turn with `--jobs`, and
+/// their output cannot be checked.
+///
 /// * `progress`: number of tests left to run.
 /// * `course`: deserialized course information.
+/// * `started`: when the runner was created, used to report the total time
+///   taken by the run.
+/// * `prepared`: results of the tests which were run in parallel, by index of
+///   their suite and index in that suite, taken as each test's turn comes.
+/// * `background`: processes started by `background` tests of the current
+///   suite, stopped once it ends.
+/// * `aborted`: whether the run was stopped before any test ran because a
+///   check failed, such as the toolchain being too old.
 #[derive(Constructor)]
 pub struct TestRunnerV1 {
-    progress: ProgressBar,
-    success: u32,
+    progress: Console,
+    results: Vec<TestOutcome>,
     pub state: TestRunnerState,
     course: JsonCourseV1,
+    options: RunnerOptions,
+    started: Instant,
+    prepared: BTreeMap<(usize, usize), TestRun>,
+    background: Background,
+    pub aborted: bool,
+}
+
+/// Summarizes the results of a run according to the scoring policy selected
+/// in `options`, see [RunnerOptions::no_optional_in_score].
+///
+/// * `options`: settings affecting how the course is run.
+/// * `course`: course which was run.
+/// * `results`: results of the tests run so far.
+fn summarize<'a>(
+    options: &RunnerOptions,
+    course: &'a JsonCourseV1,
+    results: &'a [TestOutcome],
+) -> Summary<'a> {
+    let summary = Summary::new(&course.name, course.test_count(), results);
+    match options.no_optional_in_score {
+        true => summary.without_optional(course.mandatory_count()),
+        false => summary,
+    }
+}
+
+/// Runs the tests of several suites ahead of their turn, the suites running
+/// at the same time and each running up to `jobs` of its tests at a time.
+///
+/// Results are keyed by the index of their suite and their index in it, so
+/// that they are displayed in the order of the course whatever the order in
+/// which they finished.
+///
+/// * `options`: settings affecting how the course is run.
+/// * `course`: course the suites belong to.
+/// * `batch`: indices of the suites to run.
+/// * `ctx`: settings affecting how the tests are executed.
+fn prepare(
+    options: &RunnerOptions,
+    course: &JsonCourseV1,
+    batch: &[usize],
+    ctx: &ExecContext,
+) -> BTreeMap<(usize, usize), TestRun> {
+    let fixtures = Fixtures::install(options.fixtures.as_deref());
+    // Shared by every suite of the batch since their tests run together
+    let budget = options.max_concurrent_output.map(OutputBudget::new);
+
+    thread::scope(|s| {
+        let handles = batch
+            .iter()
+            .map(|index_suite| {
+                let suite = &course.suites[*index_suite];
+                let jobs =
+                    suite.max_parallel.unwrap_or(usize::MAX).min(options.jobs);
+                // Background tests start processes which must outlive them,
+                // so they are only run in their turn
+                let indices = (0..suite.tests.len())
+                    .filter(|index_test| {
+                        will_run(options, course, *index_suite, *index_test)
+                            && !suite.tests[*index_test].background
+                    })
+                    .collect::<Vec<_>>();
+
+                let (fixtures, budget) = (&fixtures, budget.as_ref());
+                s.spawn(move || {
+                    let tests = indices
+                        .iter()
+                        .map(|index| &suite.tests[*index])
+                        .collect::<Vec<_>>();
+                    let runs = match fixtures {
+                        Ok(_) => run_parallel(&tests, jobs, budget, ctx),
+                        Err(err) => tests
+                            .iter()
+                            .map(|_| TestRun {
+                                result: TestResult::Fail(err.clone()),
+                                duration: Duration::ZERO,
+                                usage: None,
+                                timed_out: false,
+                                attempts: 1,
+                            })
+                            .collect(),
+                    };
+
+                    indices
+                        .into_iter()
+                        .map(|index_test| (*index_suite, index_test))
+                        .zip(runs)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+/// Whether a test is an optional test left to run once every mandatory test
+/// has passed, skipped when only mandatory tests need to be run.
+///
+/// * `options`: settings affecting how the course is run.
+/// * `course`: course the test belongs to.
+/// * `index_suite`: index of the suite of the test.
+/// * `index_test`: index of the test in its suite.
+fn is_trailing(
+    options: &RunnerOptions,
+    course: &JsonCourseV1,
+    index_suite: usize,
+    index_test: usize,
+) -> bool {
+    let suite = &course.suites[index_suite];
+
+    // Optional tests are only run for the score once every mandatory test has
+    // passed, which can be skipped to save time
+    options.skip_trailing_optional
+        && !suite.is_mandatory(&suite.tests[index_test])
+        && !course.has_mandatory_after(index_suite, index_test)
+}
+
+/// Whether a test is expected to run, rather than being skipped for a reason
+/// known before the run starts. Tests can still be skipped by their guard.
+///
+/// * `options`: settings affecting how the course is run.
+/// * `course`: course the test belongs to.
+/// * `index_suite`: index of the suite of the test.
+/// * `index_test`: index of the test in its suite.
+fn will_run(
+    options: &RunnerOptions,
+    course: &JsonCourseV1,
+    index_suite: usize,
+    index_test: usize,
+) -> bool {
+    let suite = &course.suites[index_suite];
+    let test = &suite.tests[index_test];
+
+    index_suite >= start_suite(options, course)
+        && (!options.skip_optional || suite.is_mandatory(test))
+        && !is_trailing(options, course, index_suite, index_test)
+        && kept_status(options, &suite.name, &test.name).is_none()
+}
+
+/// Returns the index of the suite the run starts at, see
+/// [RunnerOptions::start_at].
+///
+/// * `options`: settings affecting how the course is run.
+/// * `course`: course being run.
+fn start_suite(options: &RunnerOptions, course: &JsonCourseV1) -> usize {
+    options
+        .start_at
+        .as_deref()
+        .and_then(|suite| course.suite_index(suite))
+        .unwrap_or_default()
+}
+
+/// Returns the status a test keeps from the previous run, if any.
+///
+/// Tests which passed or were skipped in the previous run keep their status
+/// when only retrying failed tests. When continuing an interrupted run, every
+/// test which finished keeps its status.
+///
+/// * `options`: settings affecting how the course is run.
+/// * `suite`: name of the suite of the test.
+/// * `test`: name of the test.
+fn kept_status(
+    options: &RunnerOptions,
+    suite: &str,
+    test: &str,
+) -> Option<TestStatus> {
+    let retried = options
+        .retry
+        .as_ref()
+        .and_then(|state| state.status(suite, test))
+        .filter(|status| *status != TestStatus::Failed);
+    let resumed =
+        options.resume.as_ref().and_then(|state| state.status(suite, test));
+
+    retried.or(resumed)
 }
 
 impl Runner for TestRunnerV1 {
     fn run(self) -> Self {
-        let Self { progress, mut success, state, course } = self;
+        let Self {
+            progress,
+            mut results,
+            state,
+            course,
+            options,
+            started,
+            mut prepared,
+            mut background,
+            mut aborted,
+        } = self;
+        let msg = options.locale.catalog();
+        let glyphs = options.glyphs();
+        let theme = options.theme();
+        let optional = (theme.optional)(msg.optional).to_string();
 
         match state {
             // Genesis state, displays information about the course and the
             // number of exercises left.
             TestRunnerState::Loaded => {
-                progress.println(DOTCODESCHOOL.clone());
+                progress.println((theme.banner)(DOTCODESCHOOL).to_string());
 
                 progress.println(format!(
-                    "\n🎓 {} by {}",
-                    course.name.to_uppercase().white().bold(),
-                    course.instructor.white().bold()
+                    "\n{} {} {} {}",
+                    glyphs.course,
+                    (theme.heading)(&course.name.to_uppercase()),
+                    msg.by,
+                    (theme.heading)(&course.instructor)
                 ));
 
-                let exercise_count = course
-                    .suites
-                    .iter()
-                    .fold(0, |acc, suite| acc + suite.tests.len());
+                // Tests which are known ahead of time not to run are left out
+                // of the progress bar so that it fills up as tests run
+                let runnable = (0..course.suites.len())
+                    .flat_map(|i| {
+                        (0..course.suites[i].tests.len()).map(move |j| (i, j))
+                    })
+                    .filter(|(i, j)| will_run(&options, &course, *i, *j))
+                    .count();
+                progress.set_length(runnable as u64);
+
+                let exercise_count = course.test_count();
                 progress.println(format!(
-                    "\n📒 You have {} exercises left",
-                    exercise_count.to_string().bold()
+                    "\n{} {}",
+                    glyphs.exercises,
+                    Catalog::format(
+                        msg.exercises_left,
+                        "count",
+                        &(theme.emphasis)(&exercise_count.to_string())
+                            .to_string()
+                    )
                 ));
+
+                // Catches students running tests in the wrong directory or
+                // on the wrong branch. This never stops the run.
+                let problem = match check_repo(course.branch.as_deref()) {
+                    Some(RepoProblem::GitMissing) => {
+                        Some(msg.git_missing.to_string())
+                    }
+                    Some(RepoProblem::NotARepo) => {
+                        Some(msg.not_a_repo.to_string())
+                    }
+                    Some(RepoProblem::WrongBranch { expected, found }) => {
+                        Some(Catalog::format(
+                            &Catalog::format(
+                                msg.wrong_branch,
+                                "expected",
+                                &expected,
+                            ),
+                            "found",
+                            &found,
+                        ))
+                    }
+                    None => None,
+                };
+                if let Some(problem) = problem {
+                    progress.println(format!("\n{} {problem}", glyphs.warning));
+                }
+
+                // Tests relying on newer language features would otherwise
+                // fail with cryptic compiler errors
+                let toolchain = course
+                    .min_rust_version
+                    .as_deref()
+                    .and_then(|min| check_toolchain(min, msg));
+                let state = match toolchain {
+                    Some(problem) if options.strict_toolchain => {
+                        aborted = true;
+                        TestRunnerState::Failed(problem)
+                    }
+                    Some(problem) => {
+                        progress
+                            .println(format!("\n{} {problem}", glyphs.warning));
+                        TestRunnerState::Update
+                    }
+                    None => TestRunnerState::Update,
+                };
+
+                // Reports every missing binary at once rather than one failed
+                // test at a time
+                let missing = match options.preflight {
+                    true => course.missing_binaries(),
+                    false => vec![],
+                };
+                let state = match state {
+                    TestRunnerState::Update if !missing.is_empty() => {
+                        aborted = true;
+                        TestRunnerState::Failed(Catalog::format(
+                            msg.missing_binaries,
+                            "binaries",
+                            &missing.join(", "),
+                        ))
+                    }
+                    state => state,
+                };
+
                 Self {
                     progress,
-                    success,
-                    state: TestRunnerState::Update,
+                    results,
+                    state,
                     course,
+                    options,
+                    started,
+                    prepared,
+                    background,
+                    aborted,
                 }
             }
             // Initializes all submodules and checks for tests updates. This
@@ -131,7 +724,7 @@ impl Runner for TestRunnerV1 {
             // in which case it will be pulled. A new commit is then
             // created which contains the submodule update.
             TestRunnerState::Update => {
-                format_spinner(&progress);
+                format_spinner(&progress, glyphs);
 
                 let output = std::process::Command::new("git")
                     .arg("submodule")
@@ -148,9 +741,7 @@ impl Runner for TestRunnerV1 {
 
                         if line.starts_with("-") {
                             progress.set_message(
-                                "Downloading tests"
-                                    .italic()
-                                    .dimmed()
+                                (theme.muted)(msg.downloading_tests)
                                     .to_string(),
                             );
 
@@ -163,12 +754,15 @@ impl Runner for TestRunnerV1 {
                         }
                     }
                 } else {
-                    progress.println("⚠ Failed to check for updates");
+                    progress.println(format!(
+                        "{} {}",
+                        glyphs.warning, msg.update_failed
+                    ));
                 }
 
                 // Checks for updates
                 progress.set_message(
-                    "Checking for updates".italic().dimmed().to_string(),
+                    (theme.muted)(msg.checking_updates).to_string(),
                 );
 
                 let _ = std::process::Command::new("git")
@@ -186,7 +780,7 @@ impl Runner for TestRunnerV1 {
 
                     if stdout.contains("Your branch is behind") {
                         progress.set_message(
-                            "Updating tests".italic().dimmed().to_string(),
+                            (theme.muted)(msg.updating_tests).to_string(),
                         );
 
                         let _ = std::process::Command::new("git")
@@ -205,36 +799,164 @@ impl Runner for TestRunnerV1 {
                             .arg("🧪 Updated tests")
                             .output();
 
-                        progress.println("\n📝 Updated tests");
+                        progress.println(format!(
+                            "\n{} {}",
+                            glyphs.updated, msg.updated_tests
+                        ));
                     }
                 } else {
-                    progress.println("⚠ Failed to check for updates");
+                    progress.println(format!(
+                        "{} {}",
+                        glyphs.warning, msg.update_failed
+                    ));
+                }
+
+                // Earlier suites are reported as skipped so that they are left
+                // out of the score
+                let start = start_suite(&options, &course);
+                if start > 0 {
+                    progress.println(format!(
+                        "\n{} {}",
+                        glyphs.skip,
+                        Catalog::format(
+                            msg.starting_at,
+                            "suite",
+                            &course.suites[start].name
+                        )
+                    ));
+                }
+                for (index_suite, suite) in
+                    course.suites.iter().enumerate().take(start)
+                {
+                    for (index_test, test) in suite.tests.iter().enumerate() {
+                        results.push(TestOutcome {
+                            id: format!(
+                                "{}.{}",
+                                index_suite + 1,
+                                index_test + 1
+                            ),
+                            suite: suite.name.clone(),
+                            test: test.name.clone(),
+                            mandatory: suite.is_mandatory(test),
+                            status: TestStatus::Skipped,
+                            output: msg.before_start.to_string(),
+                            duration: Duration::ZERO,
+                            usage: None,
+                            timed_out: false,
+                            skip_reason: Some(SkipReason::BeforeStart),
+                            tags: test.tags.clone(),
+                            attempts: 0,
+                            flaky: false,
+                            rendered: String::new(),
+                        });
+                    }
                 }
 
-                format_bar(&progress);
+                format_bar(&progress, glyphs);
                 Self {
                     progress,
-                    success,
-                    state: TestRunnerState::NewSuite(0),
+                    results,
+                    state: match start < course.suites.len() {
+                        true => TestRunnerState::NewSuite(start),
+                        false => TestRunnerState::Passed,
+                    },
                     course,
+                    options,
+                    started,
+                    prepared,
+                    background,
+                    aborted,
                 }
             }
             // Displays the name of the current suite
             TestRunnerState::NewSuite(index_suite) => {
                 let suite = &course.suites[index_suite];
                 let suite_name =
-                    suite.name.deref().to_uppercase().bold().green();
+                    (theme.suite)(&suite.name.deref().to_uppercase());
 
-                progress.println(format!(
-                    "\n{suite_name} {}",
-                    if suite.optional { &OPTIONAL } else { "" },
-                ));
+                if !options.buffered {
+                    progress.println(format!(
+                        "\n{} {suite_name} {}",
+                        index_suite + 1,
+                        if suite.optional { &optional } else { "" },
+                    ));
+                }
+
+                // Tests are run in parallel ahead of their turn, then
+                // displayed one by one as if they had been run in order
+                let batch = (index_suite..course.suites.len())
+                    .take(options.jobs_suite.max(1))
+                    .filter(|index| {
+                        !prepared.keys().any(|(suite, _)| suite == index)
+                    })
+                    .collect::<Vec<_>>();
+                let parallel = options.jobs_suite > 1
+                    || suite
+                        .max_parallel
+                        .unwrap_or(usize::MAX)
+                        .min(options.jobs)
+                        > 1;
+                if parallel {
+                    let ctx = ExecContext {
+                        kill_grace: options.kill_grace,
+                        max_output: options.max_output,
+                        allowed: options.allowed_cmds.as_deref(),
+                        root: options.root.as_deref(),
+                        merge_output: options.merge_output,
+                        diff_context: options.diff_context,
+                        pty: options.pty,
+                        args: &options.args,
+                        ..Default::default()
+                    };
+                    let count = batch
+                        .iter()
+                        .map(|index_suite| {
+                            (0..course.suites[*index_suite].tests.len())
+                                .filter(|index_test| {
+                                    will_run(
+                                        &options,
+                                        &course,
+                                        *index_suite,
+                                        *index_test,
+                                    )
+                                })
+                                .count()
+                        })
+                        .sum::<usize>();
+
+                    let spinner_msg = Catalog::format(
+                        msg.running_tests,
+                        "count",
+                        &count.to_string(),
+                    );
+                    format_spinner(&progress, glyphs);
+                    progress
+                        .set_message((theme.muted)(&spinner_msg).to_string());
+                    prepared.extend(prepare(&options, &course, &batch, &ctx));
+                    progress.set_message("");
+                    format_bar(&progress, glyphs);
+                }
+
+                // Empty suites have no test to start from
+                let state = match (
+                    suite.tests.is_empty(),
+                    index_suite + 1 < course.suites.len(),
+                ) {
+                    (false, _) => TestRunnerState::NewTest(index_suite, 0),
+                    (true, true) => TestRunnerState::NewSuite(index_suite + 1),
+                    (true, false) => TestRunnerState::Passed,
+                };
 
                 Self {
                     progress,
-                    success,
-                    state: TestRunnerState::NewTest(index_suite, 0),
+                    results,
+                    state,
                     course,
+                    options,
+                    started,
+                    prepared,
+                    background,
+                    aborted,
                 }
             }
             // Runs the current test. This state is responsible for exiting
@@ -243,49 +965,449 @@ impl Runner for TestRunnerV1 {
             TestRunnerState::NewTest(index_suite, index_test) => {
                 let suite = &course.suites[index_suite];
                 let test = &suite.tests[index_test];
-                let test_name = test.name.to_lowercase().bold();
+                let test_name = (theme.emphasis)(&test.name.to_lowercase());
+                let mandatory = suite.is_mandatory(test);
+                let id = format!("{}.{}", index_suite + 1, index_test + 1);
 
-                progress.println(format!(
-                    "\n  🧪 Running test {test_name} {}",
-                    if test.optional { &OPTIONAL } else { "" },
-                ));
+                // Output is kept alongside the results of the test so that it
+                // can be displayed at the end of the run when buffered
+                let mut rendered = Vec::new();
+                let mut display = |text: String| {
+                    if !options.buffered {
+                        progress.println(&text);
+                    }
+                    rendered.push(text);
+                };
 
-                progress.inc(1);
+                // Freshly added tests stand out for returning students
+                let new = options
+                    .highlight_new
+                    .as_deref()
+                    .is_some_and(|version| test.is_new_since(version));
+                let badges = [
+                    (!mandatory).then(|| optional.clone()),
+                    new.then(|| (theme.emphasis)(msg.new_badge).to_string()),
+                ];
+                let badges =
+                    badges.into_iter().flatten().collect::<Vec<_>>().join(" ");
+                if !options.compact {
+                    display(format!(
+                        "\n  {} {} {id} {test_name} {badges}",
+                        glyphs.test, msg.running_test,
+                    ));
+                }
 
-                // Testing happens HERE
-                match test.run() {
-                    TestResult::Pass(stdout) => {
-                        progress.println(format_output(
-                            &stdout,
-                            &format!("✅ {}", &test.message_on_success),
-                        ));
+                if let Some(description) =
+                    test.description.as_ref().filter(|_| !options.compact)
+                {
+                    display(format!("    {}", (theme.muted)(description)));
+                }
+
+                // In compact mode, each test is a single line only followed by
+                // its output when it fails
+                let compact_name = match badges.is_empty() {
+                    true => format!("{}/{}", suite.name, test.name),
+                    false => format!("{}/{} {badges}", suite.name, test.name),
+                };
+                let compact = |status, detail: Option<&str>, duration| {
+                    format_compact(
+                        status,
+                        &compact_name,
+                        detail,
+                        duration,
+                        glyphs,
+                        theme,
+                    )
+                };
 
-                        success += 1;
+                if will_run(&options, &course, index_suite, index_test) {
+                    progress.inc(1);
+                    if options.title_progress {
+                        set_title(&progress);
                     }
-                    TestResult::Fail(stderr) => {
-                        progress.println(
-                            format_output(
-                                &stderr,
-                                &format!("❌ {}", &test.message_on_fail),
-                            )
-                            .red()
-                            .dimmed()
+                }
+
+                // Testing happens HERE, unless the test's guard says it should
+                // be skipped
+                let spinner_msg = (theme.muted)(&format!(
+                    "{} {}",
+                    msg.running_test,
+                    test.name.to_lowercase()
+                ))
+                .to_string();
+
+                // When streaming, output is displayed line by line as it is
+                // produced and only the footer of the box is left to display
+                // once the test has finished
+                let streamed = AtomicBool::new(false);
+                let on_line = |line: &str| {
+                    if !streamed.swap(true, Ordering::Relaxed) {
+                        progress.println(format_header(glyphs));
+                    }
+                    let line = test.redacted(line.to_string());
+                    progress.println(format!(
+                        "    {}{}",
+                        glyphs.box_side,
+                        truncate_line(
+                            &options.child_colors(&line, &progress),
+                            options.max_line_length
+                        )
+                    ));
+                };
+                // Resources used by every command of the test are added up
+                let usage = Mutex::new(None);
+                let on_usage = |used| record_usage(&usage, used);
+                let timed_out = AtomicBool::new(false);
+                let on_timeout = || timed_out.store(true, Ordering::Relaxed);
+                let attempts = AtomicU32::new(1);
+                let on_retry = || {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                };
+                let spawned = Mutex::new(Vec::new());
+                let on_background = |child| spawned.lock().unwrap().push(child);
+                let ctx = ExecContext {
+                    on_line: match options.stream {
+                        true => Some(&on_line),
+                        false => None,
+                    },
+                    on_usage: Some(&on_usage),
+                    on_timeout: Some(&on_timeout),
+                    on_retry: Some(&on_retry),
+                    on_background: Some(&on_background),
+                    kill_grace: options.kill_grace,
+                    max_output: options.max_output,
+                    allowed: options.allowed_cmds.as_deref(),
+                    root: options.root.as_deref(),
+                    merge_output: options.merge_output,
+                    diff_context: options.diff_context,
+                    pty: options.pty,
+                    args: &options.args,
+                    ..Default::default()
+                };
+
+                let trailing =
+                    is_trailing(&options, &course, index_suite, index_test);
+                let previous = kept_status(&options, &suite.name, &test.name);
+                let prerequisite = suite
+                    .requires_suite
+                    .as_ref()
+                    .filter(|required| {
+                        results.iter().any(|r| {
+                            &r.suite == *required
+                                && r.status == TestStatus::Failed
+                        })
+                    })
+                    .cloned();
+
+                let (status, output, duration, skip_reason) = match previous {
+                    Some(status) => {
+                        display(match options.compact {
+                            true => compact(
+                                status,
+                                Some(msg.previous_run),
+                                Duration::ZERO,
+                            ),
+                            false => (theme.skip)(&format!(
+                                "    {} {}",
+                                glyphs.skip, msg.previous_run
+                            ))
                             .to_string(),
-                        );
+                        });
+
+                        // Only tests kept as skipped are skipped by this run
+                        let reason = (status == TestStatus::Skipped)
+                            .then_some(SkipReason::PreviousRun);
+                        let output = msg.previous_run.to_string();
+                        (status, output, Duration::ZERO, reason)
+                    }
+                    None => {
+                        // Tests run in parallel already have their result
+                        let run = prepared
+                            .remove(&(index_suite, index_test))
+                            .filter(|_| prerequisite.is_none());
+                        let (result, duration) = match run {
+                            Some(run) => {
+                                *usage.lock().unwrap() = run.usage;
+                                timed_out
+                                    .store(run.timed_out, Ordering::Relaxed);
+                                attempts.store(run.attempts, Ordering::Relaxed);
+                                (run.result, run.duration)
+                            }
+                            None => {
+                                let start = Instant::now();
+                                let optional =
+                                    options.skip_optional && !mandatory;
+                                let result =
+                                    match (prerequisite, trailing, optional) {
+                                        (Some(required), ..) => {
+                                            TestResult::Skip(
+                                                SkipReason::PrerequisiteFailed,
+                                                Catalog::format(
+                                                    msg.prerequisite_failed,
+                                                    "suite",
+                                                    &required,
+                                                ),
+                                            )
+                                        }
+                                        (None, true, _) => TestResult::Skip(
+                                            SkipReason::MandatoryPassed,
+                                            msg.mandatory_passed.to_string(),
+                                        ),
+                                        (None, false, true) => {
+                                            TestResult::Skip(
+                                                SkipReason::Optional,
+                                                msg.optional_skipped
+                                                    .to_string(),
+                                            )
+                                        }
+                                        (None, false, false) => match test
+                                            .skip(&ctx)
+                                        {
+                                            Some(reason) => TestResult::Skip(
+                                                SkipReason::Guard,
+                                                reason,
+                                            ),
+                                            None => match Fixtures::install(
+                                                options.fixtures.as_deref(),
+                                            ) {
+                                                Ok(_fixtures) => {
+                                                    run_with_spinner(
+                                                        &progress,
+                                                        test,
+                                                        &spinner_msg,
+                                                        glyphs,
+                                                        &ctx,
+                                                    )
+                                                }
+                                                Err(err) => {
+                                                    TestResult::Fail(err)
+                                                }
+                                            },
+                                        },
+                                    };
+                                (result, start.elapsed())
+                            }
+                        };
 
-                        if !test.optional && !suite.optional {
-                            return Self {
-                                progress,
-                                success,
-                                state: TestRunnerState::Failed(format!(
-                                    "Failed test {test_name}"
-                                )),
-                                course,
-                            };
+                        // Skipping a mandatory test would otherwise be a way
+                        // around it when grading strictly
+                        let result = match result {
+                            TestResult::Skip(_, reason)
+                                if options.fail_on_skip && mandatory =>
+                            {
+                                TestResult::Fail(Catalog::format(
+                                    msg.skip_not_allowed,
+                                    "reason",
+                                    &reason,
+                                ))
+                            }
+                            result => result,
+                        };
+
+                        // Steps failing with their own message replace the
+                        // message of the test
+                        let message_on_fail = match &result {
+                            TestResult::StepFail { message, .. } => message,
+                            _ => &test.message_on_fail,
+                        }
+                        .clone();
+
+                        // Tests which are too slow fail only in strict mode
+                        let slow = match &result {
+                            TestResult::Pass(_) => {
+                                test.check_duration(duration)
+                            }
+                            _ => None,
+                        };
+                        let result = match slow {
+                            Some(slow) if options.strict_perf => {
+                                TestResult::Fail(slow)
+                            }
+                            Some(slow) => {
+                                display(
+                                    (theme.skip)(&format!(
+                                        "    {} {slow}",
+                                        glyphs.warning
+                                    ))
+                                    .to_string(),
+                                );
+                                result
+                            }
+                            None => result,
+                        };
+
+                        let render = |output: &str, msg: &str| match streamed
+                            .load(Ordering::Relaxed)
+                        {
+                            true => format_footer(msg, glyphs),
+                            false => format_output(
+                                &options.child_colors(output, &progress),
+                                msg,
+                                glyphs,
+                                options.max_line_length,
+                            ),
+                        };
+
+                        match result {
+                            TestResult::Pass(stdout) => {
+                                display(match options.compact {
+                                    true => compact(
+                                        TestStatus::Passed,
+                                        None,
+                                        duration,
+                                    ),
+                                    false => render(
+                                        &stdout,
+                                        &format!(
+                                            "{} {}",
+                                            glyphs.pass,
+                                            &test.message_on_success
+                                        ),
+                                    ),
+                                });
+
+                                (TestStatus::Passed, stdout, duration, None)
+                            }
+                            // Skipped tests are neither passed nor failed and
+                            // do not count towards the score
+                            TestResult::Skip(kind, reason) => {
+                                display(match options.compact {
+                                    true => compact(
+                                        TestStatus::Skipped,
+                                        Some(&reason),
+                                        duration,
+                                    ),
+                                    false => (theme.skip)(&format!(
+                                        "    {} {} ({}): {reason}",
+                                        glyphs.skip,
+                                        msg.skipped,
+                                        kind.name()
+                                    ))
+                                    .to_string(),
+                                });
+
+                                (
+                                    TestStatus::Skipped,
+                                    reason,
+                                    duration,
+                                    Some(kind),
+                                )
+                            }
+                            TestResult::Fail(stderr)
+                            | TestResult::StepFail { output: stderr, .. } => {
+                                if options.compact {
+                                    display(compact(
+                                        TestStatus::Failed,
+                                        None,
+                                        duration,
+                                    ));
+                                }
+                                display(
+                                    (theme.fail)(&render(
+                                        &stderr,
+                                        &format!(
+                                            "{} {message_on_fail}",
+                                            glyphs.fail
+                                        ),
+                                    ))
+                                    .to_string(),
+                                );
+
+                                (TestStatus::Failed, stderr, duration, None)
+                            }
                         }
                     }
                 };
 
+                let usage = usage.into_inner().unwrap();
+                if let Some(usage) = usage {
+                    log::info!(
+                        "Test {id} used {} KB of memory, {:.1}ms user and \
+                         {:.1}ms system CPU time",
+                        usage.max_rss_kb,
+                        usage.user_ms,
+                        usage.system_ms
+                    );
+                }
+
+                let attempts = attempts.into_inner();
+                results.push(TestOutcome {
+                    id: id.clone(),
+                    suite: suite.name.clone(),
+                    test: test.name.clone(),
+                    mandatory,
+                    status,
+                    output,
+                    duration,
+                    usage,
+                    timed_out: timed_out.into_inner(),
+                    skip_reason,
+                    tags: test.tags.clone(),
+                    attempts,
+                    flaky: status == TestStatus::Passed && attempts > 1,
+                    rendered: rendered.join("\n"),
+                });
+
+                // Saved as each test finishes so that an interrupted run can
+                // be continued
+                if let Some(path) = &options.state_path {
+                    RunState::new(
+                        &course.name,
+                        &results,
+                        options.retry.as_ref(),
+                    )
+                    .save(path);
+                }
+
+                // Gives live feedback on how the run is going
+                let ran = results
+                    .iter()
+                    .filter(|r| r.status != TestStatus::Skipped)
+                    .count();
+                if ran > 0 {
+                    let passing = results
+                        .iter()
+                        .filter(|r| r.status == TestStatus::Passed)
+                        .count();
+                    let score = Catalog::format(
+                        &Catalog::format(
+                            msg.passing_so_far,
+                            "passed",
+                            &passing.to_string(),
+                        ),
+                        "count",
+                        &ran.to_string(),
+                    );
+                    progress.set_message((theme.muted)(&score).to_string());
+                }
+
+                // Background processes only live as long as their suite, which
+                // ends early if a mandatory test fails
+                background.extend(spawned.into_inner().unwrap());
+                if (status == TestStatus::Failed && mandatory)
+                    || index_test + 1 == suite.tests.len()
+                {
+                    background.stop();
+                }
+
+                if status == TestStatus::Failed && mandatory {
+                    return Self {
+                        progress,
+                        results,
+                        state: TestRunnerState::Failed(Catalog::format(
+                            msg.failed_test,
+                            "test",
+                            &format!("{id} {test_name}"),
+                        )),
+                        course,
+                        options,
+                        started,
+                        prepared,
+                        background,
+                        aborted,
+                    };
+                }
+
                 // Moves on to the next text, the next suite, or marks the
                 // tests as Passed
                 match (
@@ -294,24 +1416,39 @@ impl Runner for TestRunnerV1 {
                 ) {
                     (_, true) => Self {
                         progress,
-                        success,
+                        results,
                         state: TestRunnerState::NewTest(
                             index_suite,
                             index_test + 1,
                         ),
                         course,
+                        options,
+                        started,
+                        prepared,
+                        background,
+                        aborted,
                     },
                     (true, false) => Self {
                         progress,
-                        success,
+                        results,
                         state: TestRunnerState::NewSuite(index_suite + 1),
                         course,
+                        options,
+                        started,
+                        prepared,
+                        background,
+                        aborted,
                     },
                     (false, false) => Self {
                         progress,
-                        success,
+                        results,
                         state: TestRunnerState::Passed,
                         course,
+                        options,
+                        started,
+                        prepared,
+                        background,
+                        aborted,
                     },
                 }
             }
@@ -319,15 +1456,50 @@ impl Runner for TestRunnerV1 {
             // defined in the `message_on_fail` field of a
             // Test JSON object. This state can also be used for general
             // error logging.
-            TestRunnerState::Failed(msg) => {
+            TestRunnerState::Failed(reason) => {
                 progress.finish_and_clear();
-                progress.println(format!("\n⚠ Error: {}", msg.red().bold()));
+                if options.buffered {
+                    print_grouped(&progress, &results, msg, theme);
+                }
+                progress.println(format!(
+                    "\n{} {}: {}",
+                    glyphs.warning,
+                    msg.error,
+                    (theme.error)(&reason)
+                ));
+                progress.println(format_total_time(started, msg, glyphs));
+                if let Some(slowest) = options.profile.and_then(|count| {
+                    format_slowest(&results, count, msg, glyphs, theme)
+                }) {
+                    progress.println(slowest);
+                }
+                if let Some(groups) = options.group_by.and_then(|group_by| {
+                    format_groups(&results, group_by, msg, glyphs, theme)
+                }) {
+                    progress.println(groups);
+                }
+
+                let mut summary = summarize(&options, &course, &results);
+                // Runs stopped by a failed check fail without any test failing
+                if aborted {
+                    summary.result = TestStatus::Failed;
+                }
+                if options.explain_scoring {
+                    progress
+                        .println(format_scoring(&summary, msg, glyphs, theme));
+                }
+                write_reports(&summary, &options, &progress);
 
                 Self {
                     progress,
-                    success,
+                    results,
                     state: TestRunnerState::Finish,
                     course,
+                    options,
+                    started,
+                    prepared,
+                    background,
+                    aborted,
                 }
             }
             // ALL mandatory tests passed. Displays the success rate across
@@ -337,33 +1509,112 @@ impl Runner for TestRunnerV1 {
             // student.
             TestRunnerState::Passed => {
                 progress.finish_and_clear();
-                let exercise_count = course
-                    .suites
+                if options.buffered {
+                    print_grouped(&progress, &results, msg, theme);
+                }
+                let summary = summarize(&options, &course, &results);
+                let score = format!("{:.2}", summary.score);
+
+                // A score is meaningless when every test was skipped
+                match summary.total == summary.skipped {
+                    true => progress.println(format!(
+                        "\n{} {}",
+                        glyphs.finish, msg.no_tests_run
+                    )),
+                    false => {
+                        progress.println(format!(
+                            "\n{} {}: {}%",
+                            glyphs.finish,
+                            msg.final_score,
+                            (theme.pass)(&score)
+                        ));
+                        if let Some(bonus) = summary.bonus {
+                            progress.println(format!(
+                                "{} {}",
+                                glyphs.bonus,
+                                Catalog::format(
+                                    msg.bonus_passed,
+                                    "count",
+                                    &bonus.to_string()
+                                )
+                            ));
+                        }
+
+                        // Courses can replace the messages encouraging
+                        // students
+                        let encouragement = course
+                            .score_message(summary.score)
+                            .or_else(|| {
+                                msg.score_messages
+                                    .iter()
+                                    .find(|(min, _)| {
+                                        summary.score >= f64::from(*min)
+                                    })
+                                    .map(|(_, message)| *message)
+                            })
+                            .unwrap_or_default();
+                        progress.println(
+                            (theme.emphasis)(encouragement).to_string(),
+                        );
+                    }
+                }
+                progress.println(format_total_time(started, msg, glyphs));
+                if let Some(slowest) = options.profile.and_then(|count| {
+                    format_slowest(&results, count, msg, glyphs, theme)
+                }) {
+                    progress.println(slowest);
+                }
+                if let Some(groups) = options.group_by.and_then(|group_by| {
+                    format_groups(&results, group_by, msg, glyphs, theme)
+                }) {
+                    progress.println(groups);
+                }
+                if options.explain_scoring {
+                    progress
+                        .println(format_scoring(&summary, msg, glyphs, theme));
+                }
+
+                // Optional tests which failed are listed so that motivated
+                // students can improve their score
+                let bonus = results
                     .iter()
-                    .fold(0, |acc, suite| acc + suite.tests.len());
-                let score = format!(
-                    "{:.2}",
-                    success as f64 / exercise_count as f64 * 100f64
-                );
+                    .filter(|r| !r.mandatory && r.status == TestStatus::Failed)
+                    .map(|r| format!("    - {} {} / {}", r.id, r.suite, r.test))
+                    .collect::<Vec<_>>();
+                if !bonus.is_empty() {
+                    progress.println(format!(
+                        "\n{} {}:\n{}",
+                        glyphs.bonus,
+                        msg.bonus_missed,
+                        (theme.muted)(&bonus.join("\n"))
+                    ));
+                }
 
-                progress.println(format!(
-                    "\n🏁 final score: {}%",
-                    score.green().bold()
-                ));
+                write_reports(&summary, &options, &progress);
 
                 Self {
                     progress,
-                    success,
+                    results,
                     state: TestRunnerState::Finish,
                     course,
+                    options,
+                    started,
+                    prepared,
+                    background,
+                    aborted,
                 }
             }
             // Exit state, does nothing when called.
             TestRunnerState::Finish => Self {
                 progress,
-                success,
+                results,
                 state: TestRunnerState::Finish,
                 course,
+                options,
+                started,
+                prepared,
+                background,
+                aborted,
             },
         }
     }
@@ -371,4 +1622,327 @@ impl Runner for TestRunnerV1 {
     fn state(&self) -> TestRunnerState {
         self.state.clone()
     }
+
+    fn results(&self) -> &[TestOutcome] {
+        &self.results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        parsing::{
+            test_utils::{course, optional, suite, test},
+            JsonCourseVersion,
+        },
+        runner::RunnerVersion,
+    };
+
+    /// Runs `course` to completion without displaying anything, returning the
+    /// result of every test.
+    fn run(course: JsonCourseV1, options: RunnerOptions) -> Vec<TestOutcome> {
+        RunnerVersion::from_course_with_writer(
+            JsonCourseVersion::V1(course),
+            options,
+            Box::new(std::io::sink()),
+        )
+        .collect()
+    }
+
+    /// Runs `course` to completion, returning the name of every state the
+    /// runner went through.
+    fn states(course: JsonCourseV1) -> Vec<&'static str> {
+        let mut runner = RunnerVersion::from_course_with_writer(
+            JsonCourseVersion::V1(course),
+            RunnerOptions::default(),
+            Box::new(std::io::sink()),
+        );
+        let mut states = vec![runner.state().name()];
+
+        while runner.state() != TestRunnerState::Finish {
+            runner = runner.run();
+            states.push(runner.state().name());
+        }
+
+        states
+    }
+
+    #[test]
+    fn transitions_are_listed() {
+        let courses = [
+            // Passes after an empty suite
+            course(json!([
+                suite("Empty", json!([])),
+                suite(
+                    "Suite",
+                    json!([test("A", "true"), optional(test("B", "false"))])
+                ),
+            ])),
+            // Fails on a mandatory test
+            course(json!([suite(
+                "Suite",
+                json!([test("A", "false"), test("B", "true")])
+            )])),
+            course(json!([])),
+        ];
+
+        for course in courses {
+            let states = states(course);
+            for (from, to) in states.iter().zip(&states[1..]) {
+                assert!(
+                    TestRunnerState::TRANSITIONS
+                        .iter()
+                        .any(|(f, t, _)| f == from && t == to),
+                    "transition from {from} to {to} is not listed"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tests_run_by_order_then_array_order() {
+        let mut last = test("last", "true");
+        last["order"] = json!(1);
+        let course = course(json!([suite(
+            "Suite",
+            json!([last, test("first", "true"), test("second", "true")])
+        )]));
+
+        let results = run(course, RunnerOptions::default());
+        let names = results.iter().map(|r| r.test.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(names, ["first", "second", "last"]);
+    }
+
+    #[test]
+    fn optional_suite_is_optional_but_scored() {
+        let course = course(json!([
+            optional(suite("Optional", json!([test("fails", "false")]))),
+            suite("Mandatory", json!([test("passes", "true")])),
+        ]));
+        let options = RunnerOptions::default();
+
+        // The failure does not stop the run, as the suite is optional
+        let results = run(course.clone(), options.clone());
+        let statuses = results
+            .iter()
+            .map(|r| (r.test.as_str(), r.mandatory, r.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                ("fails", false, TestStatus::Failed),
+                ("passes", true, TestStatus::Passed),
+            ]
+        );
+
+        // The failed optional test still counts towards the score
+        let summary = summarize(&options, &course, &results);
+        assert_eq!(summary.result, TestStatus::Passed);
+        assert_eq!(summary.score, 50.0);
+    }
+
+    /// A course with an optional test before its only mandatory test, and
+    /// two after it.
+    fn trailing_course() -> JsonCourseV1 {
+        course(json!([suite(
+            "Suite",
+            json!([
+                optional(test("leading", "false")),
+                test("mandatory", "true"),
+                optional(test("failing", "false")),
+                optional(test("passing", "true")),
+            ])
+        )]))
+    }
+
+    #[test]
+    fn trailing_optional_tests_run() {
+        let results = run(trailing_course(), RunnerOptions::default());
+        let statuses = results.iter().map(|r| r.status).collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            [
+                TestStatus::Failed,
+                TestStatus::Passed,
+                TestStatus::Failed,
+                TestStatus::Passed,
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_trailing_optional_skips_trailing_tests() {
+        let options = RunnerOptions {
+            skip_trailing_optional: true,
+            ..Default::default()
+        };
+
+        let results = run(trailing_course(), options);
+        let statuses = results
+            .iter()
+            .map(|r| (r.status, r.skip_reason))
+            .collect::<Vec<_>>();
+
+        // Optional tests before a mandatory one still run
+        assert_eq!(
+            statuses,
+            [
+                (TestStatus::Failed, None),
+                (TestStatus::Passed, None),
+                (TestStatus::Skipped, Some(SkipReason::MandatoryPassed)),
+                (TestStatus::Skipped, Some(SkipReason::MandatoryPassed)),
+            ]
+        );
+    }
+
+    /// Output shared with the runner displaying it.
+    #[derive(Clone, Default)]
+    struct Output(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn course_without_tests_scores_zero() {
+        let course = course(json!([suite("Empty", json!([]))]));
+        let output = Output::default();
+        let options = RunnerOptions::default();
+
+        let results = RunnerVersion::from_course_with_writer(
+            JsonCourseVersion::V1(course.clone()),
+            options.clone(),
+            Box::new(output.clone()),
+        )
+        .collect::<Vec<_>>();
+        let summary = summarize(&options, &course, &results);
+        let output = String::from_utf8(output.0.lock().unwrap().clone());
+
+        assert_eq!(summary.score, 0.0);
+        let msg = options.locale.catalog();
+        assert!(output.unwrap().contains(msg.no_tests_run));
+    }
+
+    #[test]
+    fn colors_are_stripped_from_output_written_to_a_writer() {
+        let course = course(json!([suite(
+            "Suite",
+            json!([test("colored", r"printf '\033[31mred\033[0m'")])
+        )]));
+        let output = Output::default();
+
+        RunnerVersion::from_course_with_writer(
+            JsonCourseVersion::V1(course),
+            RunnerOptions::default(),
+            Box::new(output.clone()),
+        )
+        .for_each(drop);
+        let output = String::from_utf8(output.0.lock().unwrap().clone());
+
+        let output = output.unwrap();
+        assert!(output.contains("red"));
+        assert!(!output.contains("\x1b[31m"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn background_processes_stop_with_the_runner() {
+        let mut server = test("server", "sleep 37.25");
+        server["background"] = json!(true);
+        let course = course(json!([suite(
+            "Suite",
+            json!([server, test("client", "true")])
+        )]));
+        let running = || {
+            std::process::Command::new("pgrep")
+                .args(["-fx", "sleep 37.25"])
+                .status()
+                .unwrap()
+                .success()
+        };
+
+        let mut runner = RunnerVersion::from_course_with_writer(
+            JsonCourseVersion::V1(course),
+            RunnerOptions::default(),
+            Box::new(std::io::sink()),
+        );
+        assert_eq!(runner.next().unwrap().test, "server");
+        assert!(running());
+
+        // The suite never ends, so only dropping the runner stops the server
+        drop(runner);
+        assert!(!running());
+    }
+
+    #[test]
+    fn parallel_results_keep_definition_order() {
+        let course = course(json!([
+            suite(
+                "First",
+                json!([
+                    test("slow", "sleep 0.3"),
+                    test("fast", "sleep 0.1"),
+                    test("medium", "sleep 0.2"),
+                ])
+            ),
+            suite(
+                "Second",
+                json!([test("medium", "sleep 0.2"), test("fast", "sleep 0")])
+            ),
+        ]));
+        let options =
+            RunnerOptions { jobs: 3, jobs_suite: 2, ..Default::default() };
+
+        let results = run(course, options);
+        let order = results
+            .iter()
+            .map(|r| (r.id.as_str(), r.test.as_str(), r.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            order,
+            [
+                ("1.1", "slow", TestStatus::Passed),
+                ("1.2", "fast", TestStatus::Passed),
+                ("1.3", "medium", TestStatus::Passed),
+                ("2.1", "medium", TestStatus::Passed),
+                ("2.2", "fast", TestStatus::Passed),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pty_tests_finish_alongside_slow_tests() {
+        let course = course(json!([suite(
+            "Suite",
+            json!([
+                test("first", "echo first"),
+                test("slow", "sleep 3"),
+                test("second", "echo second"),
+            ])
+        )]));
+        let options =
+            RunnerOptions { jobs: 3, pty: true, ..Default::default() };
+
+        let results = run(course, options);
+
+        // Commands spawned meanwhile must not keep the terminal of another
+        // test open, or reading its output only ends once they exit
+        for result in results.iter().filter(|r| r.test != "slow") {
+            assert_eq!(result.status, TestStatus::Passed);
+            assert!(result.duration < Duration::from_secs(2), "{result:?}");
+        }
+    }
 }